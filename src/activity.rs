@@ -3,24 +3,72 @@ use {
     crate::{
         animator::Animator,
         assets::Assets,
+        game,
         maths::*,
+        settings::Settings,
     },
     ggez::{
         GameResult,
-        input::mouse::MouseButton,
+        input::{
+            keyboard::{KeyCode, KeyMods},
+            mouse::MouseButton,
+        },
     },
 };
 
 pub struct Context<'a> {
     pub ctx:      &'a mut ggez::Context,
-    pub assets:   &'a Assets,
+    pub assets:   &'a mut Assets,
     pub animator: &'a mut dyn Animator,
+    pub settings: &'a Settings,
+}
+
+// where an activity wants the app to go next
+pub enum Switch {
+    // set up the next game, starting from this config
+    Configure(game::Config),
+    // look at the high scores for boards like this
+    Scores(game::Config),
+    // look at the lifetime statistics for boards like this
+    Stats(game::Config),
+    // abandon the current game for a new one
+    Play(game::Config),
+    // return to the activity that was left for configuring
+    Back,
+}
+
+// size the window and screen to fit, if they don't already. the screen is always `width` by
+// `height`; the window is that times `scale`.
+pub fn resize_window(ctx: &mut ggez::Context, scale: f32, width: f32, height: f32) -> GameResult {
+    use ggez::graphics::{
+        Rect, drawable_size, screen_coordinates, set_drawable_size, set_screen_coordinates,
+    };
+
+    // setting the size also undoes any maximising, so only when it's actually wrong
+    let (w, h) = drawable_size(ctx);
+    if (w - width * scale).abs() >= 1. || (h - height * scale).abs() >= 1. {
+        set_drawable_size(ctx, width * scale, height * scale)?;
+    }
+
+    let screen = screen_coordinates(ctx);
+    if screen.x != 0. || screen.y != 0. || screen.w != width || screen.h != height {
+        set_screen_coordinates(ctx, Rect::new(0., 0., width, height))?;
+    }
+
+    Ok(())
 }
 
 pub trait Activity {
     fn mouse_down<'a> (&mut self, context: Context<'a>, button: MouseButton, position: P2);
     fn mouse_up  <'a> (&mut self, context: Context<'a>, button: MouseButton, position: P2);
+    fn mouse_move<'a> (&mut self, context: Context<'a>, position: P2);
+    // the player has resized the window, to `width` by `height` in screen pixels
+    fn resized   <'a> (&mut self, context: Context<'a>, width: f32, height: f32);
+    fn key_down  <'a> (&mut self, context: Context<'a>, key: KeyCode, mods: KeyMods);
     fn draw      <'a> (&mut self, context: Context<'a>) -> GameResult;
+    fn close     <'a> (&mut self, context: Context<'a>);
+    fn focus     <'a> (&mut self, context: Context<'a>, focused: bool);
     fn dirty(&self) -> bool;
+    fn switch(&mut self) -> Option<Switch>;
 }
 
@@ -1,8 +1,10 @@
 
 use {
     crate::{
+        game::MAX_MINES_PER_TILE,
+        grid::{Tiling, HEX_RADIUS, MAX_NEIGHBOURS},
         maths::*,
-        play::*, // TODO remove
+        play::BAR_SCALE,
     },
     ggez::{
         Context,
@@ -15,15 +17,21 @@ pub struct Texts {
     pub flag: Text,
     pub tick: Text,
     pub nope: Text,
+    pub question: Text,
 
     pub okay: Text,
     pub woop: Text,
     pub ohno: Text,
     pub boop: Text,
 
+    pub play:  Text,
+    pub pause: Text,
+    pub gear:  Text,
+
     pub hazards: Vec<Text>,
 
-    pub stat: Text,
+    pub stat:  Text,
+    pub label: Text,
 
     pub digits: Vec<Text>,
 }
@@ -41,7 +49,13 @@ fn digit_color(digit: usize) -> Color {
         [H, H, H, 1.], // 7
         [H, H, H, 1.], // 8
     ];
-    DIGIT_COLORS[digit - 1].into()
+
+    // past eight, which only the bigger neighbourhoods and crowded tiles reach, the colours come
+    // round again paler
+    let [r, g, b, a] = DIGIT_COLORS[(digit - 1) % 8];
+    let pale = (((digit - 1) / 8) as f32 * 0.25).min(0.75);
+    let pale = |c: f32| c + (1. - c) * pale;
+    [pale(r), pale(g), pale(b), a].into()
 }
 
 fn load_emoji(ch: char, font: Font, size: f32, color: impl Into<Color>)
@@ -56,10 +70,26 @@ fn load_emoji(ch: char, font: Font, size: f32, color: impl Into<Color>)
     text
 }
 
+// a count for a tile `size` across. more digits are squeezed narrower, keeping the height of one.
+fn load_digits(n: usize, font: Font, size: f32) -> Text {
+    let width = match n {
+        0 ..= 9   => size,
+        10 ..= 99 => size * 0.55,
+        _         => size * 0.4,
+    };
+    let frag = TextFragment::new(n.to_string())
+        .color(digit_color(n))
+        .font(font)
+        .scale(Scale { x: width, y: size });
+    let mut text = Text::new(frag);
+    text.set_bounds(P2::new(size, size), Align::Center);
+    text
+}
+
 impl Texts {
-    fn new(ctx: &mut Context) -> GameResult<Texts> {
-        let symbola = Font::new(ctx, "Symbola.ttf")?;
-        let signika = Font::new(ctx, "Signika-SemiBold.ttf")?;
+    // with glyphs drawn for tiles `tile` across, so they stay sharp at any size
+    fn new(symbola: Font, signika: Font, tile: f32) -> Texts {
+        let bar = tile * BAR_SCALE;
 
         //🦟
         //🧨
@@ -67,52 +97,85 @@ impl Texts {
         const HAZARDS: &'static str = "💀☢☣⚡💣🦇🦈🦀💩🦂🦑🤖🕷🦖🕱";
 
         let texts = Texts {
-            flag: load_emoji('⚑',  symbola, TILE_SIZE, (0.4, 0.7, 1.0)),
-            tick: load_emoji('✓',  symbola, TILE_SIZE, (0.0, 0.0, 0.0)),
-            nope: load_emoji('✗',  symbola, TILE_SIZE, (1.0, 1.0, 1.0)),
+            flag: load_emoji('⚑',  symbola, tile, (0.4, 0.7, 1.0)),
+            tick: load_emoji('✓',  symbola, tile, (0.0, 0.0, 0.0)),
+            nope: load_emoji('✗',  symbola, tile, (1.0, 1.0, 1.0)),
+            question: load_emoji('?', signika, tile, (0.2, 0.2, 0.2)),
 
-            okay: load_emoji('🙂', symbola, BAR_HEIGHT, (0.6, 0.6, 0.6)),
-            woop: load_emoji('🤩', symbola, BAR_HEIGHT, (1.0, 0.4, 0.7)),
-            ohno: load_emoji('🤯', symbola, BAR_HEIGHT, (0.8, 0.3, 0.0)),
-            boop: load_emoji('😲', symbola, BAR_HEIGHT, (0.3, 0.6, 1.0)),
+            okay: load_emoji('🙂', symbola, bar, (0.6, 0.6, 0.6)),
+            woop: load_emoji('🤩', symbola, bar, (1.0, 0.4, 0.7)),
+            ohno: load_emoji('🤯', symbola, bar, (0.8, 0.3, 0.0)),
+            boop: load_emoji('😲', symbola, bar, (0.3, 0.6, 1.0)),
+
+            play:  load_emoji('▶', symbola, bar, (0.6, 0.6, 0.6)),
+            pause: load_emoji('⏸', symbola, bar, (0.6, 0.6, 0.6)),
+            gear:  load_emoji('⚙', symbola, bar, (0.6, 0.6, 0.6)),
 
             hazards: HAZARDS.chars()
-                .map(|ch| load_emoji(ch, symbola, TILE_SIZE, (1., 1., 1.)))
+                .map(|ch| load_emoji(ch, symbola, tile, (1., 1., 1.)))
                 .collect(),
 
             stat: {
-                let mut text = load_emoji('⚑', symbola, BAR_HEIGHT, (1., 1., 1.));
-                text.add(("", signika, BAR_HEIGHT));
+                let mut text = load_emoji('⚑', symbola, bar, (1., 1., 1.));
+                text.add(("", signika, bar));
                 text
             },
 
-            digits: (1 ..= 8)
-                .map(|digit| {
-                    let ch = std::char::from_digit(digit as u32, 10).unwrap();
-                    load_emoji(ch, signika, TILE_SIZE, digit_color(digit))
-                })
+            label: Text::new(("", signika, bar * 0.6)),
+
+            // every neighbour can hold a tileful of mines
+            digits: (1 ..= MAX_NEIGHBOURS * MAX_MINES_PER_TILE)
+                .map(|n| load_digits(n, signika, tile))
                 .collect(),
         };
 
-        Ok(texts)
+        texts
     }
 }
 
+// a tile's square, from (1, 1) so that neighbours have a gap between them
+fn tile_square(ctx: &mut Context, mode: DrawMode, tile: f32) -> GameResult<Mesh> {
+    Mesh::new_rectangle(ctx, mode, Rect::new(1., 1., tile - 2., tile - 2.), (1., 1., 1.).into())
+}
+
+// a pointy-topped hexagon `tile` across, centred in the square a tile's glyphs are drawn in, and
+// again a pixel smaller all round
+fn tile_hexagon(ctx: &mut Context, mode: DrawMode, tile: f32) -> GameResult<Mesh> {
+    let radius = tile * HEX_RADIUS - 1.;
+    let points: Vec<P2> = (0 .. 6)
+        .map(|i| {
+            let theta = (i as f32 * 60. - 90.).to_radians();
+            P2::new(tile * 0.5 + radius * theta.cos(), tile * 0.5 + radius * theta.sin())
+        })
+        .collect();
+    Mesh::new_polygon(ctx, mode, &points, (1., 1., 1.).into())
+}
+
 pub struct Assets {
+    // how big a tile the meshes and texts below are made for
+    pub tile:   f32,
     pub square: Mesh,
+    pub hexagon: Mesh,
+    // around the keyboard cursor
+    pub outline: Mesh,
+    pub hex_outline: Mesh,
     pub circle: std::rc::Rc<Mesh>,
     pub star:   std::rc::Rc<Mesh>,
     pub texts:  Texts,
+
+    symbola: Font,
+    signika: Font,
 }
 
 impl Assets {
-    pub fn load(ctx: &mut ggez::Context) -> GameResult<Assets> {
-        let square = Mesh::new_rectangle(
-            ctx,
-            DrawMode::fill(),
-            Rect::new(1., 1., TILE_SIZE - 2., TILE_SIZE - 2.),
-            (1., 1., 1.).into()
-        )?;
+    pub fn load(ctx: &mut ggez::Context, tile: f32) -> GameResult<Assets> {
+        let symbola = Font::new(ctx, "Symbola.ttf")?;
+        let signika = Font::new(ctx, "Signika-SemiBold.ttf")?;
+
+        let square  = tile_square(ctx, DrawMode::fill(),     tile)?;
+        let outline = tile_square(ctx, DrawMode::stroke(2.), tile)?;
+        let hexagon     = tile_hexagon(ctx, DrawMode::fill(),     tile)?;
+        let hex_outline = tile_hexagon(ctx, DrawMode::stroke(2.), tile)?;
 
         let circle = Mesh::new_circle(
             ctx,
@@ -138,16 +201,48 @@ impl Assets {
             (1., 1., 1.).into()
         )?;
 
-        let texts = Texts::new(ctx)?;
-
         let assets = Assets {
+            tile,
             square,
+            hexagon,
+            outline,
+            hex_outline,
             circle: std::rc::Rc::new(circle),
             star:   std::rc::Rc::new(star),
-            texts,
+            texts:  Texts::new(symbola, signika, tile),
+            symbola,
+            signika,
         };
 
         Ok(assets)
     }
+
+    // remake everything that depends on the tile size, if it's changed
+    pub fn set_tile(&mut self, ctx: &mut ggez::Context, tile: f32) -> GameResult {
+        if tile == self.tile { return Ok(()); }
+
+        self.square  = tile_square(ctx, DrawMode::fill(),     tile)?;
+        self.outline = tile_square(ctx, DrawMode::stroke(2.), tile)?;
+        self.hexagon     = tile_hexagon(ctx, DrawMode::fill(),     tile)?;
+        self.hex_outline = tile_hexagon(ctx, DrawMode::stroke(2.), tile)?;
+        self.texts   = Texts::new(self.symbola, self.signika, tile);
+        self.tile    = tile;
+        Ok(())
+    }
+
+    // what a tile is drawn as
+    pub fn cell(&self, tiling: Tiling) -> &Mesh {
+        match tiling {
+            Tiling::Square => &self.square,
+            Tiling::Hex    => &self.hexagon,
+        }
+    }
+
+    pub fn cell_outline(&self, tiling: Tiling) -> &Mesh {
+        match tiling {
+            Tiling::Square => &self.outline,
+            Tiling::Hex    => &self.hex_outline,
+        }
+    }
 }
 
@@ -0,0 +1,239 @@
+
+// which mouse buttons and keys do what, written as e.g. `mouse-left` or `ctrl+shift+z`
+
+use {
+    ggez::input::{
+        keyboard::{KeyCode, KeyMods},
+        mouse::MouseButton,
+    },
+    std::{fmt, str::FromStr},
+};
+
+const KEYS: &[(&str, KeyCode)] = &[
+    ("a", KeyCode::A), ("b", KeyCode::B), ("c", KeyCode::C), ("d", KeyCode::D),
+    ("e", KeyCode::E), ("f", KeyCode::F), ("g", KeyCode::G), ("h", KeyCode::H),
+    ("i", KeyCode::I), ("j", KeyCode::J), ("k", KeyCode::K), ("l", KeyCode::L),
+    ("m", KeyCode::M), ("n", KeyCode::N), ("o", KeyCode::O), ("p", KeyCode::P),
+    ("q", KeyCode::Q), ("r", KeyCode::R), ("s", KeyCode::S), ("t", KeyCode::T),
+    ("u", KeyCode::U), ("v", KeyCode::V), ("w", KeyCode::W), ("x", KeyCode::X),
+    ("y", KeyCode::Y), ("z", KeyCode::Z),
+    ("0", KeyCode::Key0), ("1", KeyCode::Key1), ("2", KeyCode::Key2), ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4), ("5", KeyCode::Key5), ("6", KeyCode::Key6), ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8), ("9", KeyCode::Key9),
+    ("f1", KeyCode::F1), ("f2", KeyCode::F2), ("f3",  KeyCode::F3),  ("f4",  KeyCode::F4),
+    ("f5", KeyCode::F5), ("f6", KeyCode::F6), ("f7",  KeyCode::F7),  ("f8",  KeyCode::F8),
+    ("f9", KeyCode::F9), ("f10", KeyCode::F10), ("f11", KeyCode::F11), ("f12", KeyCode::F12),
+    ("up", KeyCode::Up), ("down", KeyCode::Down), ("left", KeyCode::Left), ("right", KeyCode::Right),
+    ("space",     KeyCode::Space),
+    ("return",    KeyCode::Return),
+    ("escape",    KeyCode::Escape),
+    ("tab",       KeyCode::Tab),
+    ("backspace", KeyCode::Back),
+    ("delete",    KeyCode::Delete),
+    ("insert",    KeyCode::Insert),
+    ("home",      KeyCode::Home),
+    ("end",       KeyCode::End),
+    ("pageup",    KeyCode::PageUp),
+    ("pagedown",  KeyCode::PageDown),
+];
+
+const BUTTONS: &[(&str, MouseButton)] = &[
+    ("mouse-left",   MouseButton::Left),
+    ("mouse-right",  MouseButton::Right),
+    ("mouse-middle", MouseButton::Middle),
+];
+
+// the modifiers that have to match exactly for a key binding to apply
+const MODS: &[(&str, KeyMods)] = &[
+    ("ctrl",  KeyMods::CTRL),
+    ("shift", KeyMods::SHIFT),
+    ("alt",   KeyMods::ALT),
+];
+
+fn relevant_mods(mods: KeyMods) -> KeyMods {
+    mods & (KeyMods::CTRL | KeyMods::SHIFT | KeyMods::ALT)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Mouse(MouseButton),
+    // two buttons down together, in either order
+    Buttons(MouseButton, MouseButton),
+    Key(KeyMods, KeyCode),
+}
+
+impl Input {
+    pub fn is_button(self, button: MouseButton) -> bool {
+        self == Input::Mouse(button)
+    }
+
+    pub fn is_buttons(self, a: MouseButton, b: MouseButton) -> bool {
+        self == Input::Buttons(a, b) || self == Input::Buttons(b, a)
+    }
+
+    pub fn is_key(self, key: KeyCode, mods: KeyMods) -> bool {
+        self == Input::Key(relevant_mods(mods), key)
+    }
+}
+
+fn write_button(f: &mut fmt::Formatter, button: MouseButton) -> fmt::Result {
+    match BUTTONS.iter().find(|&&(_, b)| b == button) {
+        Some((name, _)) => f.write_str(name),
+        None            => write!(f, "{:?}", button),
+    }
+}
+
+fn find_button(name: &str) -> Option<MouseButton> {
+    BUTTONS.iter().find(|&&(n, _)| n == name).map(|&(_, button)| button)
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Input::Mouse(button) => write_button(f, button),
+
+            Input::Buttons(a, b) => {
+                write_button(f, a)?;
+                f.write_str("+")?;
+                write_button(f, b)
+            }
+
+            Input::Key(mods, key) => {
+                for (name, m) in MODS {
+                    if mods.contains(*m) {
+                        write!(f, "{}+", name)?;
+                    }
+                }
+
+                match KEYS.iter().find(|&&(_, k)| k == key) {
+                    Some((name, _)) => f.write_str(name),
+                    None            => write!(f, "{:?}", key),
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Input, String> {
+        let s = s.to_lowercase();
+        if let Some(button) = find_button(&s) {
+            return Ok(Input::Mouse(button));
+        }
+
+        let mut parts: Vec<&str> = s.split('+').collect();
+        if let [a, b] = parts[..] {
+            if let (Some(a), Some(b)) = (find_button(a), find_button(b)) {
+                return Ok(Input::Buttons(a, b));
+            }
+        }
+
+        let key = parts.pop().unwrap_or_default();
+        let key = match KEYS.iter().find(|&&(name, _)| name == key) {
+            Some(&(_, key)) => key,
+            None            => return Err(format!("unknown key '{}'", key)),
+        };
+
+        let mut mods = KeyMods::NONE;
+        for part in parts {
+            match MODS.iter().find(|&&(name, _)| name == part) {
+                Some(&(_, m)) => mods |= m,
+                None          => return Err(format!("unknown modifier '{}'", part)),
+            }
+        }
+
+        Ok(Input::Key(mods, key))
+    }
+}
+
+// any of several inputs, written space-separated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Binding(pub Vec<Input>);
+
+impl Binding {
+    pub fn has_button(&self, button: MouseButton) -> bool {
+        self.0.iter().any(|input| input.is_button(button))
+    }
+
+    pub fn has_buttons(&self, a: MouseButton, b: MouseButton) -> bool {
+        self.0.iter().any(|input| input.is_buttons(a, b))
+    }
+
+    pub fn has_key(&self, key: KeyCode, mods: KeyMods) -> bool {
+        self.0.iter().any(|input| input.is_key(key, mods))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, input) in self.0.iter().enumerate() {
+            if i > 0 { f.write_str(" ")?; }
+            write!(f, "{}", input)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Binding, String> {
+        s.split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Binding)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    // on the tile under the mouse, or under the keyboard cursor
+    pub dig:       Binding,
+    pub flag:      Binding,
+    // dig the tile under the mouse or cursor only if it's an uncovered number
+    pub chord:     Binding,
+    pub undo:      Binding,
+    pub redo:      Binding,
+    pub hint:      Binding,
+    pub up:        Binding,
+    pub down:      Binding,
+    pub left:      Binding,
+    pub right:     Binding,
+    pub restart:   Binding,
+    pub configure: Binding,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings {
+            dig: Binding(vec![
+                Input::Mouse(MouseButton::Left),
+                Input::Key(KeyMods::NONE, KeyCode::Space),
+            ]),
+            flag: Binding(vec![
+                Input::Mouse(MouseButton::Right),
+                Input::Key(KeyMods::NONE, KeyCode::F),
+            ]),
+            chord: Binding(vec![
+                Input::Mouse(MouseButton::Middle),
+                Input::Buttons(MouseButton::Left, MouseButton::Right),
+                Input::Key(KeyMods::NONE, KeyCode::C),
+            ]),
+            undo:      Binding(vec![Input::Key(KeyMods::CTRL, KeyCode::Z)]),
+            redo: Binding(vec![
+                Input::Key(KeyMods::CTRL, KeyCode::Y),
+                Input::Key(KeyMods::CTRL | KeyMods::SHIFT, KeyCode::Z),
+            ]),
+            hint:      Binding(vec![Input::Key(KeyMods::NONE, KeyCode::H)]),
+            up:        Binding(vec![Input::Key(KeyMods::NONE, KeyCode::Up)]),
+            down:      Binding(vec![Input::Key(KeyMods::NONE, KeyCode::Down)]),
+            left:      Binding(vec![Input::Key(KeyMods::NONE, KeyCode::Left)]),
+            right:     Binding(vec![Input::Key(KeyMods::NONE, KeyCode::Right)]),
+            restart: Binding(vec![
+                Input::Key(KeyMods::NONE, KeyCode::R),
+                Input::Key(KeyMods::NONE, KeyCode::F2),
+            ]),
+            configure: Binding(vec![Input::Key(KeyMods::NONE, KeyCode::Escape)]),
+        }
+    }
+}
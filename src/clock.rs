@@ -0,0 +1,76 @@
+
+// where a game gets the time from, in milliseconds. the game only ever asks for the time, so
+// swapping in a clock that's driven by hand replays the timing of a recorded game exactly.
+
+use std::{
+    cell::Cell,
+    time::Instant,
+};
+
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+// real time, which can be stopped and started again
+pub struct WallClock {
+    // time banked from before the last stop
+    banked:  Cell<u64>,
+    running: Cell<Option<Instant>>,
+}
+
+impl WallClock {
+    // a running clock, reading `start` to begin with
+    pub fn new(start: u64) -> WallClock {
+        WallClock {
+            banked:  Cell::new(start),
+            running: Cell::new(Some(Instant::now())),
+        }
+    }
+
+    pub fn stop(&self) {
+        self.banked.set(self.now());
+        self.running.set(None);
+    }
+
+    pub fn start(&self) {
+        if self.running.get().is_none() {
+            self.running.set(Some(Instant::now()));
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.get().is_some()
+    }
+}
+
+impl Default for WallClock {
+    fn default() -> WallClock {
+        WallClock::new(0)
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self) -> u64 {
+        let running = self.running.get()
+            .map_or(0, |since| since.elapsed().as_millis() as u64);
+        self.banked.get() + running
+    }
+}
+
+// reads whatever it was last set to
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<u64>,
+}
+
+impl ManualClock {
+    pub fn set(&self, now: u64) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
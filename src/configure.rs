@@ -2,33 +2,493 @@
 use {
     crate::{
         activity::*,
+        game::{self, Outline},
+        grid::{Mask, Neighbourhood, Tiling},
         maths::*,
+        play::{BAR_HEIGHT, TILE_SIZE},
     },
     ggez::{
         GameResult,
-        input::mouse::MouseButton,
+        input::{
+            keyboard::{KeyCode, KeyMods},
+            mouse::MouseButton,
+        },
     },
 };
 
+const WINDOW_WIDTH: f32 = 360.;
+const ROW_HEIGHT:   f32 = BAR_HEIGHT * 0.8;
+
+const MIN_WIDTH:  usize = 8;
+const MAX_WIDTH:  usize = 50;
+const MIN_HEIGHT: usize = 4;
+const MAX_HEIGHT: usize = 30;
+
+// name, width, height, mines
+type Preset = (&'static str, usize, usize, usize);
+
+const PRESETS: [Preset; 3] = [
+    ("beginner",      9,  9, 10),
+    ("intermediate", 16, 16, 40),
+    ("expert",       30, 16, 99),
+];
+
+// with six neighbours rather than eight the counts run lower, so the same density would be easier
+// and more often leave big openings. these are about three quarters as dense.
+const HEX_PRESETS: [Preset; 3] = [
+    ("beginner",      9,  9,  8),
+    ("intermediate", 16, 16, 30),
+    ("expert",       30, 16, 74),
+];
+
+fn presets(tiling: Tiling) -> &'static [Preset] {
+    match tiling {
+        Tiling::Square => &PRESETS,
+        Tiling::Hex    => &HEX_PRESETS,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Preset,
+    Width,
+    Height,
+    Mines,
+    MinesPerTile,
+    Seed,
+    Generation,
+    Opening,
+    Chording,
+    Win,
+    Flags,
+    QuestionMarks,
+    Topology,
+    Tiling,
+    Neighbourhood,
+    Outline,
+    Scores,
+    Stats,
+    Start,
+    Back,
+}
+
+const ROWS: [Row; 20] = [
+    Row::Preset,
+    Row::Width,
+    Row::Height,
+    Row::Mines,
+    Row::MinesPerTile,
+    Row::Seed,
+    Row::Generation,
+    Row::Opening,
+    Row::Chording,
+    Row::Win,
+    Row::Flags,
+    Row::QuestionMarks,
+    Row::Topology,
+    Row::Tiling,
+    Row::Neighbourhood,
+    Row::Outline,
+    Row::Scores,
+    Row::Stats,
+    Row::Start,
+    Row::Back,
+];
+
+impl Row {
+    fn label(self) -> &'static str {
+        match self {
+            Row::Preset        => "preset",
+            Row::Width         => "width",
+            Row::Height        => "height",
+            Row::Mines         => "mines",
+            Row::MinesPerTile  => "mines per tile",
+            Row::Seed          => "seed",
+            Row::Generation    => "generation",
+            Row::Opening       => "opening",
+            Row::Chording      => "chording",
+            Row::Win           => "win when",
+            Row::Flags         => "flags",
+            Row::QuestionMarks => "question marks",
+            Row::Topology      => "edges",
+            Row::Tiling        => "tiles",
+            Row::Neighbourhood => "counting",
+            Row::Outline       => "outline",
+            Row::Scores        => "high scores",
+            Row::Stats         => "statistics",
+            Row::Start         => "start",
+            Row::Back          => "back",
+        }
+    }
+}
+
+// the next option along from `current`, wrapping around
+fn cycle<T> (all: &[T], current: T, step: isize) -> T
+    where T: Clone + PartialEq
+{
+    let i = all.iter().position(|x| *x == current).unwrap_or(0) as isize;
+    all[(i + step.signum()).rem_euclid(all.len() as isize) as usize].clone()
+}
+
+fn clamp(value: usize, step: isize, min: usize, max: usize) -> usize {
+    (value as isize + step).max(min as isize).min(max as isize) as usize
+}
+
+// a board outline of the player's own, drawn in a text file kept with the settings: a line per row,
+// with `#` for a cell and `.` for a gap
+const MASK_FILE: &str = "mask";
+
+fn load_mask(ctx: &ggez::Context) -> Option<Mask> {
+    let path = ggez::filesystem::user_config_dir(ctx).join(MASK_FILE);
+    let text = std::fs::read_to_string(&path).ok()?;
+    let rows: Vec<&str> = text.lines().map(str::trim).filter(|row| !row.is_empty()).collect();
+    match rows.join("/").parse() {
+        Ok(mask) => Some(mask),
+        Err(err) => {
+            eprintln!("couldn't read board outline from {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
 pub struct Configure {
+    config:   game::Config,
+    // the custom outline on offer, if there is one
+    custom:   Option<Mask>,
+    selected: usize,
+    dirty:    bool,
+    switch:   Option<Switch>,
 }
 
 impl Configure {
+    pub fn new(ctx: &ggez::Context, config: game::Config) -> Configure {
+        // a fresh drawing takes over from the one already in use
+        let custom = load_mask(ctx).or_else(|| match &config.outline {
+            Outline::Custom(mask) => Some(mask.clone()),
+            _                     => None,
+        });
+
+        Configure {
+            config,
+            custom,
+            selected: 0,
+            dirty:    true,
+            switch:   None,
+        }
+    }
+
+    // the outlines to choose between, custom last
+    fn outlines(&self) -> Vec<Outline> {
+        let mut outlines = Outline::built_in().to_vec();
+        outlines.extend(self.custom.clone().map(Outline::Custom));
+        outlines
+    }
+
+    fn is_custom(&self) -> bool {
+        matches!(self.config.outline, Outline::Custom(_))
+    }
+
+    // changing the board's size leaves a custom outline behind
+    fn resize(&mut self, width: usize, height: usize, n_mines: usize) {
+        if self.is_custom() && (width, height) != (self.config.width, self.config.height) {
+            self.config.outline = Outline::Rectangle;
+        }
+        self.config = game::Config { width, height, n_mines, ..self.config.clone() };
+    }
+
+    fn preset(&self) -> Option<usize> {
+        let config = &self.config;
+        presets(config.tiling).iter().position(|&(_, width, height, n_mines)| {
+            (width, height, n_mines) == (config.width, config.height, config.n_mines)
+        })
+    }
+
+    fn max_mines(&self) -> usize {
+        (self.config.n_cells() - 1) * self.config.mines_per_tile
+    }
+
+    fn value(&self, row: Row) -> String {
+        let config = &self.config;
+        match row {
+            Row::Preset => match self.preset() {
+                Some(i) => presets(config.tiling)[i].0.to_owned(),
+                None    => "custom".to_owned(),
+            },
+            Row::Width  => config.width.to_string(),
+            Row::Height => config.height.to_string(),
+            Row::Mines  => {
+                let density = 100. * config.n_mines as f32 / config.n_cells() as f32;
+                format!("{} ({:.0}%)", config.n_mines, density)
+            }
+            Row::MinesPerTile => config.mines_per_tile.to_string(),
+            Row::Seed => match config.seed {
+                Some(seed) => seed.to_string(),
+                None       => "random".to_owned(),
+            },
+            Row::Generation    => config.generation.to_string(),
+            Row::Opening       => config.opening.to_string(),
+            Row::Chording      => config.chording.to_string(),
+            Row::Win           => config.win.to_string(),
+            Row::Flags         => config.flags.to_string(),
+            Row::QuestionMarks => if config.question_marks { "on" } else { "off" }.to_owned(),
+            Row::Topology      => config.topology.to_string(),
+            Row::Tiling        => config.tiling.to_string(),
+            // hexagons only ever count the six they touch
+            Row::Neighbourhood => match config.tiling {
+                Tiling::Square => config.neighbourhood.to_string(),
+                Tiling::Hex    => "touching".to_owned(),
+            },
+            Row::Outline => match &config.outline {
+                Outline::Custom(mask) => format!("custom {}×{}", mask.width(), mask.height()),
+                outline               => outline.to_string(),
+            },
+            Row::Scores | Row::Stats | Row::Start | Row::Back => String::new(),
+        }
+    }
+
+    fn adjust(&mut self, row: Row, step: isize) {
+        let max_mines = self.max_mines();
+        let config = &mut self.config;
+        match row {
+            Row::Preset => {
+                let presets = presets(config.tiling);
+                let next = match self.preset() {
+                    Some(i) => (i as isize + step.signum()).rem_euclid(presets.len() as isize) as usize,
+                    None    => 0,
+                };
+                let (_, width, height, n_mines) = presets[next];
+                self.resize(width, height, n_mines);
+            }
+            Row::Width  => {
+                let width = clamp(config.width, step, MIN_WIDTH, MAX_WIDTH);
+                self.resize(width, self.config.height, self.config.n_mines);
+            }
+            Row::Height => {
+                let height = clamp(config.height, step, MIN_HEIGHT, MAX_HEIGHT);
+                self.resize(self.config.width, height, self.config.n_mines);
+            }
+            Row::Mines  => config.n_mines = clamp(config.n_mines, step, 1, max_mines),
+            Row::MinesPerTile => {
+                config.mines_per_tile = clamp(config.mines_per_tile, step, 1, game::MAX_MINES_PER_TILE);
+            }
+            // stepping below zero goes back to random
+            Row::Seed => config.seed = match config.seed {
+                Some(seed) if step < 0 => seed.checked_sub(-step as u64),
+                Some(seed)             => Some(seed.saturating_add(step as u64)),
+                None if step > 0       => Some(0),
+                None                   => None,
+            },
+            Row::Generation    => config.generation = cycle(game::Generation::ALL, config.generation, step),
+            Row::Opening       => config.opening    = cycle(game::Opening::ALL,    config.opening,    step),
+            Row::Chording      => config.chording   = cycle(game::Chording::ALL,   config.chording,   step),
+            Row::Win           => config.win        = cycle(game::WinRule::ALL,    config.win,        step),
+            Row::Flags         => config.flags      = cycle(game::FlagLimit::ALL,  config.flags,      step),
+            Row::QuestionMarks => config.question_marks = !config.question_marks,
+            Row::Topology      => config.topology   = cycle(game::Topology::ALL,   config.topology,   step),
+            Row::Tiling        => {
+                // keep to the same preset, as it suits the new tiles
+                let preset = self.preset();
+                let tiling = cycle(Tiling::ALL, self.config.tiling, step);
+                self.config.tiling = tiling;
+                if let Some(i) = preset {
+                    let (_, width, height, n_mines) = presets(tiling)[i];
+                    self.resize(width, height, n_mines);
+                }
+            }
+            Row::Neighbourhood => if config.tiling == Tiling::Square {
+                config.neighbourhood = cycle(Neighbourhood::ALL, config.neighbourhood, step);
+            },
+            Row::Outline       => {
+                let outline = cycle(&self.outlines(), self.config.outline.clone(), step);
+                // a custom outline brings its own size
+                if let Outline::Custom(mask) = &outline {
+                    self.config.width  = mask.width();
+                    self.config.height = mask.height();
+                }
+                self.config.outline = outline;
+            }
+            Row::Scores        => self.switch = Some(Switch::Scores(self.config.clone())),
+            Row::Stats         => self.switch = Some(Switch::Stats(self.config.clone())),
+            Row::Start         => self.switch = Some(Switch::Play(self.config.clone())),
+            Row::Back          => self.switch = Some(Switch::Back),
+        }
+
+        // no-guess boards only have a mine or none to a tile. whichever was just picked wins.
+        let config = &mut self.config;
+        if config.generation == game::Generation::NoGuess && config.mines_per_tile > 1 {
+            match row {
+                Row::Generation => config.mines_per_tile = 1,
+                _               => config.generation = game::Generation::Random,
+            }
+        }
+
+        // hexagon rows need to come in pairs to join top to bottom. choosing that makes the board
+        // taller or shorter; changing anything else gives up the join.
+        let config = &self.config;
+        if config.tiling == Tiling::Hex && config.topology.wrap().y && config.height % 2 == 1 {
+            match row {
+                Row::Topology => {
+                    let height = if config.height < MAX_HEIGHT { config.height + 1 } else { config.height - 1 };
+                    self.resize(self.config.width, height, self.config.n_mines);
+                }
+                _ => self.config.topology = match config.topology {
+                    game::Topology::Torus => game::Topology::WrapX,
+                    _                     => game::Topology::Flat,
+                },
+            }
+        }
+
+        // a smaller board, or fewer mines to a tile, may not fit the mines any more
+        self.config.n_mines = self.config.n_mines.min(self.max_mines());
+    }
+
+    fn type_digit(&mut self, digit: u64) {
+        let seed = self.config.seed.unwrap_or(0);
+        if let Some(seed) = seed.checked_mul(10).and_then(|seed| seed.checked_add(digit)) {
+            self.config.seed = Some(seed);
+        }
+    }
+
+    fn erase_digit(&mut self) {
+        self.config.seed = match self.config.seed {
+            Some(seed) if seed >= 10 => Some(seed / 10),
+            _                        => None,
+        };
+    }
 }
 
 impl Activity for Configure {
-    fn mouse_down<'a> (&mut self, context: Context<'a>, button: MouseButton, position: P2) {
+    fn mouse_down<'a> (
+        &mut self,
+        Context { ctx, .. }: Context<'a>,
+        button: MouseButton, position: P2)
+    {
+        let i = (position.y / ROW_HEIGHT) as usize;
+        let row = match ROWS.get(i) {
+            Some(&row) => row,
+            None       => return
+        };
+
+        let mods = ggez::input::keyboard::active_mods(ctx);
+        let size = if mods.contains(KeyMods::SHIFT) { 10 } else { 1 };
+        let step = match button {
+            MouseButton::Left  =>  size,
+            MouseButton::Right => -size,
+            _                  => return
+        };
+
+        self.selected = i;
+        self.adjust(row, step);
+        self.dirty = true;
+    }
+
+    fn mouse_up<'a> (&mut self, _context: Context<'a>, _button: MouseButton, _position: P2) {
+    }
+
+    fn mouse_move<'a> (&mut self, _context: Context<'a>, _position: P2) {
     }
 
-    fn mouse_up<'a> (&mut self, context: Context<'a>, button: MouseButton, position: P2) {
+    fn resized<'a> (&mut self, _context: Context<'a>, _width: f32, _height: f32) {
+    }
+
+    fn key_down<'a> (&mut self, _context: Context<'a>, key: KeyCode, mods: KeyMods) {
+        let row = ROWS[self.selected];
+        let size = if mods.contains(KeyMods::SHIFT) { 10 } else { 1 };
+
+        match key {
+            KeyCode::Up     => self.selected = (self.selected + ROWS.len() - 1) % ROWS.len(),
+            KeyCode::Down   => self.selected = (self.selected + 1) % ROWS.len(),
+            KeyCode::Left   => self.adjust(row, -size),
+            KeyCode::Right  => self.adjust(row,  size),
+            KeyCode::Return => match row {
+                Row::Scores | Row::Stats | Row::Back => self.adjust(row, 1),
+                _                                    => self.adjust(Row::Start, 1),
+            },
+            KeyCode::Escape => self.adjust(Row::Back, 1),
+
+            KeyCode::Back   if row == Row::Seed => self.erase_digit(),
+            KeyCode::Delete if row == Row::Seed => self.config.seed = None,
+            _ if row == Row::Seed => {
+                use KeyCode::*;
+                let digit = match key {
+                    Key0 | Numpad0 => 0, Key1 | Numpad1 => 1, Key2 | Numpad2 => 2,
+                    Key3 | Numpad3 => 3, Key4 | Numpad4 => 4, Key5 | Numpad5 => 5,
+                    Key6 | Numpad6 => 6, Key7 | Numpad7 => 7, Key8 | Numpad8 => 8,
+                    Key9 | Numpad9 => 9,
+                    _              => return
+                };
+                self.type_digit(digit);
+            }
+
+            _ => return
+        }
+
+        self.dirty = true;
     }
 
-    fn draw<'a> (&mut self, context: Context<'a>) -> GameResult {
+    fn draw<'a> (
+        &mut self,
+        Context { ctx, assets, settings, .. }: Context<'a>)
+        -> GameResult
+    {
+        use ggez::graphics::{Align, clear, draw, DrawParam};
+
+        // laid out for tiles of the usual size, and scaled as a whole
+        assets.set_tile(ctx, TILE_SIZE)?;
+        resize_window(ctx, settings.scale, WINDOW_WIDTH, ROWS.len() as f32 * ROW_HEIGHT)?;
+
+        let b = settings.theme.background();
+        clear(ctx, (b, b, b).into());
+
+        for (i, &row) in ROWS.iter().enumerate() {
+            let top = i as f32 * ROW_HEIGHT;
+
+            if i == self.selected {
+                draw(ctx, &assets.square, DrawParam::new()
+                    .dest(P2::new(0., top))
+                    .scale(V2::new(WINDOW_WIDTH / TILE_SIZE, ROW_HEIGHT / TILE_SIZE))
+                    .color((0.2, 0.2, 0.2).into()))?;
+            }
+
+            let text_top = top + (ROW_HEIGHT - BAR_HEIGHT * 0.6) * 0.5;
+            let bounds = P2::new(WINDOW_WIDTH - 24., ROW_HEIGHT);
+
+            let mut label = assets.texts.label.clone();
+            label.fragments_mut()[0].text = row.label().to_owned();
+            match row {
+                Row::Scores | Row::Stats | Row::Start | Row::Back => {
+                    label.set_bounds(bounds, Align::Center);
+                    draw(ctx, &label, DrawParam::new().dest(P2::new(12., text_top)))?;
+                }
+
+                _ => {
+                    label.fragments_mut()[0].color = Some((0.6, 0.6, 0.6).into());
+                    label.set_bounds(bounds, Align::Left);
+                    draw(ctx, &label, DrawParam::new().dest(P2::new(12., text_top)))?;
+
+                    let mut value = assets.texts.label.clone();
+                    value.fragments_mut()[0].text = self.value(row);
+                    value.set_bounds(bounds, Align::Right);
+                    draw(ctx, &value, DrawParam::new().dest(P2::new(12., text_top)))?;
+                }
+            }
+        }
+
+        self.dirty = false;
         Ok(())
     }
 
+    fn close<'a> (&mut self, _context: Context<'a>) {
+    }
+
+    fn focus<'a> (&mut self, _context: Context<'a>, _focused: bool) {
+    }
+
     fn dirty(&self) -> bool {
-        false
+        self.dirty
     }
-}
 
+    fn switch(&mut self) -> Option<Switch> {
+        self.switch.take()
+    }
+}
@@ -0,0 +1,184 @@
+
+// the plain-text layout shared by the files the game writes: a header line naming the kind of
+// file and its version, a block of `key value` fields, then a blank line and any body lines.
+
+use {
+    crate::game::Config,
+    std::{
+        fmt,
+        io::{self, BufRead, Write},
+        str::FromStr,
+    },
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // not the kind of file expected, or written by a version we can't read
+    Header(String),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err)                  => write!(f, "{}", err),
+            Error::Header(found)            => write!(f, "unrecognised header '{}'", found),
+            Error::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for Error { }
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+pub fn parse<T> (line: usize, word: Option<&str>, what: &str) -> Result<T, Error>
+    where T: FromStr
+{
+    let word = word.ok_or_else(|| Error::Syntax { line, message: format!("missing {}", what) })?;
+    word.parse().map_err(|_| Error::Syntax { line, message: format!("bad {} '{}'", what, word) })
+}
+
+pub struct Fields {
+    fields: Vec<(usize, String, String)>,
+}
+
+impl Fields {
+    fn find(&self, key: &str) -> Option<&(usize, String, String)> {
+        self.fields.iter().find(|(_, k, _)| k == key)
+    }
+
+    pub fn get<T> (&self, key: &str) -> Result<T, Error> where T: FromStr {
+        match self.find(key) {
+            Some((line, _, value)) => parse(*line, Some(value), key),
+            None => Err(Error::Syntax { line: 0, message: format!("missing field '{}'", key) }),
+        }
+    }
+
+    // every key present, with the line it's on
+    pub fn keys(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.fields.iter().map(|(line, key, _)| (*line, key.as_str()))
+    }
+
+    // for fields added since a kind of file was first written
+    pub fn get_or<T> (&self, key: &str, default: T) -> Result<T, Error> where T: FromStr {
+        match self.find(key) {
+            Some((line, _, value)) => parse(*line, Some(value), key),
+            None                   => Ok(default),
+        }
+    }
+}
+
+pub struct Reader<R> {
+    lines: io::Lines<R>,
+    line:  usize,
+}
+
+impl<R> Reader<R> where R: BufRead {
+    pub fn new(read: R) -> Reader<R> {
+        Reader { lines: read.lines(), line: 0 }
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, Error> {
+        self.line += 1;
+        Ok(self.lines.next().transpose()?)
+    }
+
+    pub fn header(&mut self, kind: &str, version: u32) -> Result<(), Error> {
+        let found = self.next_line()?.unwrap_or_default();
+        if found == format!("{} {}", kind, version) {
+            Ok(())
+        }
+        else {
+            Err(Error::Header(found))
+        }
+    }
+
+    // reads up to the blank line ending the field block
+    pub fn fields(&mut self) -> Result<Fields, Error> {
+        let mut fields = Vec::new();
+        while let Some(text) = self.next_line()? {
+            let text = text.trim();
+            if text.is_empty() {
+                break;
+            }
+
+            let mut split = text.splitn(2, ' ');
+            let key   = split.next().unwrap_or_default();
+            let value = split.next().unwrap_or_default().trim();
+            fields.push((self.line, key.to_owned(), value.to_owned()));
+        }
+
+        Ok(Fields { fields })
+    }
+
+    // the next non-empty body line, with its line number
+    pub fn body_line(&mut self) -> Result<Option<(usize, String)>, Error> {
+        while let Some(text) = self.next_line()? {
+            if !text.trim().is_empty() {
+                return Ok(Some((self.line, text)));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+pub fn write_config(w: &mut impl Write, config: &Config) -> io::Result<()> {
+    writeln!(w, "width {}",      config.width)?;
+    writeln!(w, "height {}",     config.height)?;
+    writeln!(w, "mines {}",      config.n_mines)?;
+    match config.seed {
+        Some(seed) => writeln!(w, "seed {}", seed)?,
+        None       => writeln!(w, "seed random")?,
+    }
+    writeln!(w, "generation {}", config.generation)?;
+    writeln!(w, "opening {}",    config.opening)?;
+    writeln!(w, "chording {}",   config.chording)?;
+    writeln!(w, "win {}",        config.win)?;
+    writeln!(w, "flags {}",      config.flags)?;
+    writeln!(w, "question-marks {}", config.question_marks)?;
+    writeln!(w, "topology {}",   config.topology)?;
+    writeln!(w, "tiling {}",     config.tiling)?;
+    writeln!(w, "neighbourhood {}", config.neighbourhood)?;
+    writeln!(w, "outline {}",    config.outline)?;
+    writeln!(w, "mines-per-tile {}", config.mines_per_tile)?;
+    Ok(())
+}
+
+pub fn read_config(fields: &Fields) -> Result<Config, Error> {
+    let seed: String = fields.get("seed")?;
+    let seed = match seed.as_str() {
+        "random" => None,
+        _        => Some(fields.get("seed")?),
+    };
+
+    let config = Config {
+        width:      fields.get("width")?,
+        height:     fields.get("height")?,
+        n_mines:    fields.get("mines")?,
+        seed,
+        generation: fields.get("generation")?,
+        opening:    fields.get("opening")?,
+        chording:   fields.get("chording")?,
+        win:        fields.get("win")?,
+        flags:      fields.get("flags")?,
+        question_marks: fields.get("question-marks")?,
+        topology:   fields.get("topology")?,
+        tiling:     fields.get("tiling")?,
+        neighbourhood: fields.get("neighbourhood")?,
+        outline:    fields.get("outline")?,
+        mines_per_tile: fields.get("mines-per-tile")?,
+    };
+
+    if !config.is_possible() {
+        return Err(Error::Syntax { line: 0, message: "impossible board dimensions".to_owned() });
+    }
+
+    Ok(config)
+}
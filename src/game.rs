@@ -1,40 +1,58 @@
 
 use {
-    crate::grid::{Coords, Grid},
-    std::collections::VecDeque,
-    ggez::nalgebra::Vector2 as V2,
+    crate::{
+        clock::{Clock, WallClock},
+        grid::{Coords, Grid, Mask, Neighbourhood, Shape, Tiling, Wrap},
+        solver,
+    },
+    std::{collections::VecDeque, rc::Rc},
+    nalgebra::Vector2 as V2,
     rand::{distributions::Uniform, Rng, SeedableRng},
     rand_pcg::Pcg32,
 };
 
-fn flood_clear(grid: &mut Grid<Tile>, start: Coords) {
-    let w = grid.width() as i32;
-    let h = grid.height() as i32;
-
-    let mut q = VecDeque::new();
-    q.push_back(start);
+// `Display` and `FromStr` for a fieldless enum, using the names it goes by in files, plus a list
+// of every variant
+macro_rules! names {
+    ($ty:ident { $($variant:ident => $name:literal),* $(,)? }) => {
+        impl $ty {
+            pub const ALL: &'static [$ty] = &[$($ty::$variant),*];
+        }
 
-    let mut do_adj = |q: &mut VecDeque<Coords>, at: Coords| {
-        if at.x < 0 || at.y < 0 || at.x >= w || at.y >= h {
-            return;
+        impl std::fmt::Display for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(match self { $($ty::$variant => $name),* })
+            }
         }
 
-        let tile = &mut grid[at];
-        if tile.kind == TileKind::Dirt && tile.state != TileState::Uncovered {
-            tile.state = TileState::Uncovered;
-            if tile.n_near == 0 { q.push_back(at); }
+        impl std::str::FromStr for $ty {
+            type Err = String;
+            fn from_str(s: &str) -> Result<$ty, String> {
+                match s {
+                    $($name => Ok($ty::$variant),)*
+                    _ => Err(format!("unknown {} '{}'", stringify!($ty).to_lowercase(), s)),
+                }
+            }
         }
-    };
+    }
+}
+
+fn flood_clear(grid: &mut Grid<Tile>, start: Coords) {
+    let mut q = VecDeque::new();
+    q.push_back(start);
 
     while let Some(p) = q.pop_front() {
-        do_adj(&mut q, p + V2::new(-1,  0));
-        do_adj(&mut q, p + V2::new( 0, -1));
-        do_adj(&mut q, p + V2::new( 1,  0));
-        do_adj(&mut q, p + V2::new( 0,  1));
-        do_adj(&mut q, p + V2::new(-1, -1));
-        do_adj(&mut q, p + V2::new( 1, -1));
-        do_adj(&mut q, p + V2::new(-1,  1));
-        do_adj(&mut q, p + V2::new( 1,  1));
+        // across the seams too, where the board wraps
+        let adjacent: Vec<Coords> = grid.neighbours(p).collect();
+        for at in adjacent {
+            let tile = &mut grid[at];
+            if tile.kind == TileKind::Dirt && tile.state != TileState::Uncovered
+                && tile.flags() == 0
+            {
+                tile.state = TileState::Uncovered;
+                if tile.n_near == 0 { q.push_back(at); }
+            }
+        }
     }
 }
 
@@ -45,19 +63,35 @@ pub enum Status {
     Dead
 }
 
+names!(Status {
+    Playing => "playing",
+    Won     => "won",
+    Dead    => "dead",
+});
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TileKind {
     Dirt,
-    Mine
+    // how many, from one up to the config's `mines_per_tile`
+    Mine(usize)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TileState {
-    Covered(bool),
+    Covered(Mark),
     Uncovered
 }
 
-#[derive(Clone, Copy, Debug)]
+// what the player has left on a covered tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mark {
+    Blank,
+    // how many are stacked up, for tiles that can hold more than one mine
+    Flag(usize),
+    Question
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Tile {
     pub kind:    TileKind,
     pub state:   TileState,
@@ -68,94 +102,532 @@ impl Tile {
     fn new() -> Tile {
         Tile {
             kind:   TileKind::Dirt,
-            state:  TileState::Covered(false),
+            state:  TileState::Covered(Mark::Blank),
             n_near: 0,
         }
     }
+
+    pub fn mines(&self) -> usize {
+        match self.kind {
+            TileKind::Dirt    => 0,
+            TileKind::Mine(n) => n,
+        }
+    }
+
+    pub fn flags(&self) -> usize {
+        match self.state {
+            TileState::Covered(Mark::Flag(n)) => n,
+            _                                 => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Generation {
+    // mines are placed uniformly at random
+    Random,
+    // only layouts the solver can clear from the first dig are accepted
+    NoGuess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opening {
+    // the game makes the first dig itself, somewhere with no mines nearby
+    Auto,
+    // mines are placed on the first dig, never under the tile dug
+    SafeTile,
+    // as above, also keeping the tiles around it clear
+    SafeArea,
+}
+
+names!(Generation {
+    Random  => "random",
+    NoGuess => "no-guess",
+});
+
+names!(Opening {
+    Auto     => "auto",
+    SafeTile => "safe-tile",
+    SafeArea => "safe-area",
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chording {
+    // digging a number opens its neighbours only once it has as many flags around it
+    Standard,
+    // digging a number opens its neighbours regardless
+    Unconditional,
 }
 
-#[derive(Clone, Copy, Debug)]
+names!(Chording {
+    Standard      => "standard",
+    Unconditional => "unconditional",
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinRule {
+    // every safe tile uncovered and every mine flagged
+    Flagged,
+    // every safe tile uncovered; the mines are flagged for you
+    Cleared,
+}
+
+names!(WinRule {
+    Flagged => "flagged",
+    Cleared => "cleared",
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagLimit {
+    // no more flags than there are mines
+    Limited,
+    // as many as you like, with the count of those remaining going negative
+    Unlimited,
+}
+
+names!(FlagLimit {
+    Limited   => "limited",
+    Unlimited => "unlimited",
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    // a plain rectangle
+    Flat,
+    // the left and right edges are joined
+    WrapX,
+    // the top and bottom edges are joined
+    WrapY,
+    // both, making a torus
+    Torus,
+}
+
+names!(Topology {
+    Flat  => "flat",
+    WrapX => "wrap-x",
+    WrapY => "wrap-y",
+    Torus => "torus",
+});
+
+names!(Tiling {
+    Square => "square",
+    Hex    => "hex",
+});
+
+names!(Neighbourhood {
+    Adjacent   => "adjacent",
+    Orthogonal => "orthogonal",
+    Knight     => "knight",
+    Radius2    => "radius-2",
+});
+
+impl Topology {
+    pub fn wrap(self) -> Wrap {
+        Wrap {
+            x: self == Topology::WrapX || self == Topology::Torus,
+            y: self == Topology::WrapY || self == Topology::Torus,
+        }
+    }
+}
+
+// the board's outline, within its width and height
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outline {
+    Rectangle,
+    // the biggest ellipse that fits
+    Circle,
+    Diamond,
+    // a circle with a hole through the middle
+    Ring,
+    // the player's own, which only fits a board its size
+    Custom(Mask),
+}
+
+impl Outline {
+    // all but custom, which the player has to provide
+    pub fn built_in() -> [Outline; 4] {
+        [Outline::Rectangle, Outline::Circle, Outline::Diamond, Outline::Ring]
+    }
+
+    // which cells of a board are inside the outline, or None for all of them
+    pub fn mask(&self, tiling: Tiling, width: usize, height: usize) -> Option<Mask> {
+        let (w, h) = tiling.extent(width, height);
+        let inside = |test: fn(f32, f32) -> bool| {
+            Some(Mask::new_generate(width, height, |p| {
+                // from -1 to 1 across the board, through cells' centres
+                let (x, y) = tiling.center(p);
+                test(2. * x / w - 1., 2. * y / h - 1.)
+            }))
+        };
+
+        match self {
+            Outline::Rectangle    => None,
+            Outline::Circle       => inside(|u, v| u * u + v * v <= 1.),
+            Outline::Diamond      => inside(|u, v| u.abs() + v.abs() <= 1.),
+            Outline::Ring         => inside(|u, v| (0.2 ..= 1.).contains(&(u * u + v * v))),
+            Outline::Custom(mask) => Some(mask.clone()),
+        }
+    }
+}
+
+// one word in files: the name of a built-in outline, or a custom one's mask
+impl std::fmt::Display for Outline {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Outline::Rectangle    => f.write_str("rectangle"),
+            Outline::Circle       => f.write_str("circle"),
+            Outline::Diamond      => f.write_str("diamond"),
+            Outline::Ring         => f.write_str("ring"),
+            Outline::Custom(mask) => write!(f, "{}", mask),
+        }
+    }
+}
+
+impl std::str::FromStr for Outline {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Outline, String> {
+        match s {
+            "rectangle" => Ok(Outline::Rectangle),
+            "circle"    => Ok(Outline::Circle),
+            "diamond"   => Ok(Outline::Diamond),
+            "ring"      => Ok(Outline::Ring),
+            _           => Ok(Outline::Custom(s.parse()?)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Config {
-    pub width:   usize,
-    pub height:  usize,
-    pub n_mines: usize,
-    pub seed:    Option<u64>,
+    pub width:      usize,
+    pub height:     usize,
+    pub n_mines:    usize,
+    // the most mines one tile can hold
+    pub mines_per_tile: usize,
+    pub seed:       Option<u64>,
+    pub generation: Generation,
+    pub opening:    Opening,
+    pub chording:   Chording,
+    pub win:        WinRule,
+    pub flags:      FlagLimit,
+    // whether flagging cycles through a question mark on its way back to blank
+    pub question_marks: bool,
+    pub topology:   Topology,
+    pub tiling:     Tiling,
+    pub neighbourhood: Neighbourhood,
+    pub outline:    Outline,
+}
+
+impl Config {
+    pub fn shape(&self) -> Shape {
+        Shape { tiling: self.tiling, neighbourhood: self.neighbourhood, wrap: self.topology.wrap() }
+    }
+
+    pub fn mask(&self) -> Option<Mask> {
+        self.outline.mask(self.tiling, self.width, self.height)
+    }
+
+    // how many tiles the board has, leaving out any gaps
+    pub fn n_cells(&self) -> usize {
+        self.mask().map_or(self.width * self.height, |mask| mask.count())
+    }
+
+    // whether there's room on the board for its mines, a custom outline fits it, and its edges
+    // can join up the way its topology says
+    pub fn is_possible(&self) -> bool {
+        let fits = match &self.outline {
+            Outline::Custom(mask) => (mask.width(), mask.height()) == (self.width, self.height),
+            _                     => true,
+        };
+
+        // leaving at least one tile clear
+        let room = self.n_cells().saturating_sub(1) * self.mines_per_tile;
+        // the solver only knows tiles of one mine or none, so it can't vouch for the others
+        let provable = self.generation != Generation::NoGuess || self.mines_per_tile == 1;
+        // hexagon rows only line up across the top and bottom if there's an even number of them
+        let joins = self.tiling == Tiling::Square || !self.topology.wrap().y || self.height % 2 == 0;
+        fits && provable && joins && self.width > 0 && self.height > 0
+            && (1 ..= MAX_MINES_PER_TILE).contains(&self.mines_per_tile) && self.n_mines <= room
+    }
 }
 
+// kept to a single digit, so that tiles stay one character each in saves
+pub const MAX_MINES_PER_TILE: usize = 9;
+
+// give up on finding a no-guess layout after this many tries, and settle for the last one. the
+// game owns up to it, and isn't filed as no-guess.
+const MAX_NO_GUESS_ATTEMPTS: usize = 1000;
+
+// lay out mines, keeping them off `start` if given. returns the tiles and where to dig first.
+fn generate(config: &Config, rng: &mut Pcg32, start: Option<Coords>) -> (Grid<Tile>, Coords) {
+    let mut tiles: Grid<Tile> = Grid::new_fill(config.width, config.height, Tile::new())
+        .shaped(config.shape())
+        .masked(config.mask());
+
+    let i_distro = Uniform::new(0, config.width  as i32);
+    let j_distro = Uniform::new(0, config.height as i32);
+
+    // only clear around the start if that leaves room for all the mines
+    let clear_area = config.opening == Opening::SafeArea
+        && start.map_or(false, |start| {
+            let area = tiles.neighbours(start).count() + 1;
+            (config.n_cells() - area) * config.mines_per_tile >= config.n_mines
+        });
+
+    let around: Vec<Coords> = match start {
+        Some(start) if clear_area => tiles.neighbours(start).collect(),
+        _                         => Vec::new(),
+    };
+    let excluded = |ij: Coords| start == Some(ij) || around.contains(&ij);
+
+    // tiles with no mine yet. one is always kept that way, which only takes any keeping when
+    // tiles can hold several mines.
+    let mut n_clear = tiles.indices().count();
+
+    // randomly position mines
+    for _ in 0 .. config.n_mines {
+        // choose a spot, looping until we pick a spot we haven't picked before
+        let ij = loop {
+            let i = rng.sample(i_distro);
+            let j = rng.sample(j_distro);
+            let ij = Coords::new(i, j);
+            let last_clear = tiles[ij].mines() == 0 && n_clear == 1;
+            if tiles.contains(ij) && !excluded(ij) && tiles[ij].mines() < config.mines_per_tile
+                && !last_clear
+            {
+                break ij;
+            }
+        };
+
+        // place the mine
+        if tiles[ij].mines() == 0 { n_clear -= 1; }
+        tiles[ij].kind = TileKind::Mine(tiles[ij].mines() + 1);
+
+        // increase near-counts of adjacent tiles, and of the mine itself
+        let adjacent: Vec<Coords> = tiles.neighbours(ij).collect();
+        tiles[ij].n_near += 1;
+        for adj in adjacent {
+            tiles[adj].n_near += 1;
+        }
+    }
+
+    // choose where to make the first dig, if the player isn't going to. a crowded board may
+    // have no empty tile, and then the safe tile with the fewest mines around will do.
+    let start_ij = start.unwrap_or_else(|| {
+        if tiles.iter().any(|tile| tile.n_near == 0) {
+            loop {
+                let i = rng.sample(i_distro);
+                let j = rng.sample(j_distro);
+                let ij = Coords::new(i, j);
+                if tiles.contains(ij) && tiles[ij].n_near == 0 {
+                    break ij;
+                }
+            }
+        }
+        else {
+            tiles.indices()
+                .filter(|&ij| tiles[ij].mines() == 0)
+                .min_by_key(|&ij| tiles[ij].n_near)
+                .unwrap()
+        }
+    });
+
+    (tiles, start_ij)
+}
+
+// something the player can do to the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Dig(Coords),
+    Flag(Coords),
+    Undo,
+    Redo,
+}
+
+// everything an action can change
+#[derive(Clone)]
+struct Snapshot {
+    placed:  bool,
+    tiles:   Grid<Tile>,
+    status:  Status,
+    n_flags: isize,
+}
+
+// how long the player has spent on a game, going by its clock. it starts with their first action
+// and stops while the game is over.
+#[derive(Clone, Copy, Debug, Default)]
+struct Timer {
+    started: bool,
+    banked:  u64,
+    // the clock's reading when it last started running, if it's running now
+    since:   Option<u64>,
+}
+
+#[derive(Clone)]
 pub struct State {
     config:  Config,
+    seed:    u64,
+    placed:  bool,
     tiles:   Grid<Tile>,
     status:  Status,
-    n_flags: usize,
+    n_flags: isize,
+
+    undo_stack: Vec<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    undo_used:  bool,
+    hint_used:  bool,
+    // no-guess generation gave up, and the layout may need a guess
+    unproven:   bool,
+
+    clock: Rc<dyn Clock>,
+    timer: Timer,
 }
 
 impl State {
     pub fn new(config: Config) -> State {
-        let mut tiles: Grid<Tile> = Grid::new_fill(config.width, config.height, Tile::new());
-
         let seed = config.seed.unwrap_or(rand::rngs::OsRng.gen());
-        let mut rng = Pcg32::seed_from_u64(seed);
-        let i_distro = Uniform::new(0, config.width  as i32);
-        let j_distro = Uniform::new(0, config.height as i32);
-
-        // randomly position mines
-        for _ in 0 .. config.n_mines {
-            // choose a spot, looping until we pick a spot we haven't picked before
-            let ij = loop {
-                let i = rng.sample(&i_distro);
-                let j = rng.sample(&j_distro);
-                let ij = Coords::new(i, j);
-                match tiles[ij].kind {
-                    TileKind::Dirt => break ij,
-                    TileKind::Mine => continue
-                }
-            };
 
-            // place the mine
-            tiles[ij].kind = TileKind::Mine;
+        let auto = config.opening == Opening::Auto;
+        let mut state = State {
+            seed,
+            placed: false,
+            tiles: Grid::new_fill(config.width, config.height, Tile::new())
+                .shaped(config.shape())
+                .masked(config.mask()),
+            status: Status::Playing,
+            n_flags: config.n_mines as isize,
+            config,
 
-            // increase near-counts of adjacent tiles
-            for dj in -1 ..= 1 {
-                for di in -1 ..= 1 {
-                    let adj = ij + V2::new(di, dj);
-                    if tiles.in_bounds(adj) {
-                        tiles[adj].n_near += 1;
-                    }
-                }
-            }
-        }
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            undo_used:  false,
+            hint_used:  false,
+            unproven:   false,
 
-        // make the first dig automatically
-        let start_ij = loop {
-            let i = rng.sample(&i_distro);
-            let j = rng.sample(&j_distro);
-            let ij = Coords::new(i, j);
-            if tiles[ij].n_near == 0 {
-                break ij;
-            }
+            clock: Rc::new(WallClock::default()),
+            timer: Timer::default(),
         };
 
+        if auto {
+            // make the first dig automatically. it isn't the player's, so it can't be undone.
+            let start_ij = state.place(None);
+            state.dig_tiles(start_ij);
+        }
+
+        state
+    }
+
+    // rebuild a game from its tiles' kinds and states, working out the rest. the seed must be
+    // filled in, and `elapsed` is the time on the timer if it had started.
+    pub(crate) fn from_parts(
+        config:    Config,
+        mut tiles: Grid<Tile>,
+        placed:    bool,
+        status:    Status,
+        undo_used: bool,
+        hint_used: bool,
+        elapsed:   Option<u64>)
+        -> State
+    {
+        let indices: Vec<Coords> = tiles.indices().collect();
+        // counted the way `generate` counts them, a mine's own tile included
+        for &ij in &indices {
+            let n_near = tiles.neighbours(ij)
+                .map(|adj| tiles[adj].mines())
+                .sum::<usize>();
+            tiles[ij].n_near = n_near + tiles[ij].mines();
+        }
+
+        let flagged: usize = tiles.iter().map(Tile::flags).sum();
+
         let mut state = State {
-            config,
+            seed: config.seed.unwrap_or_default(),
+            placed,
             tiles,
-            status: Status::Playing,
-            n_flags: config.n_mines,
-        };
+            status,
+            n_flags: config.n_mines as isize - flagged as isize,
+            config,
+
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            undo_used,
+            hint_used,
+            unproven: false,
 
-        state.dig(start_ij);
+            clock: Rc::new(WallClock::default()),
+            timer: Timer {
+                started: elapsed.is_some(),
+                banked:  elapsed.unwrap_or(0),
+                since:   None,
+            },
+        };
 
+        state.update_timer(false);
         state
     }
 
+    // lay out the mines, always from the same seed, so the layout depends only on the seed
+    // and where the player starts
+    fn place(&mut self, start: Option<Coords>) -> Coords {
+        let mut rng = Pcg32::seed_from_u64(self.seed);
+
+        let mut attempts = 0;
+        loop {
+            let (tiles, start_ij) = generate(&self.config, &mut rng, start);
+            let candidate = State { tiles, placed: true, ..self.clone() };
+
+            attempts += 1;
+            let (accept, unproven) = match self.config.generation {
+                Generation::Random  => (true, false),
+                // not a possible config, but should one turn up it takes its chances
+                Generation::NoGuess if self.config.mines_per_tile > 1 => (true, true),
+                Generation::NoGuess => {
+                    let proven = candidate.provable(start_ij);
+                    (proven || attempts >= MAX_NO_GUESS_ATTEMPTS, !proven)
+                }
+            };
+
+            if accept {
+                *self = State { unproven, ..candidate };
+                return start_ij;
+            }
+        }
+    }
+
+    // play the game out on a copy using only deduction, and see if that clears the board
+    fn provable(&self, start_ij: Coords) -> bool {
+        let mut state = self.clone();
+        state.dig_tiles(start_ij);
+        while state.status == Status::Playing {
+            let deductions = solver::deduce(&state);
+            if deductions.is_empty() {
+                break;
+            }
+
+            for ij in deductions.mines {
+                state.flag_tile(ij);
+            }
+
+            for ij in deductions.safe {
+                // an earlier dig may have flooded this one already
+                if state.tiles[ij].state == TileState::Covered(Mark::Blank) {
+                    state.dig_tiles(ij);
+                }
+            }
+        }
+
+        state.cleared()
+    }
+
     fn uncover(&mut self, ij: Coords) -> Option<bool> {
         let tile = &mut self.tiles[ij];
-        if tile.state != TileState::Covered(false) {
-            return None;
+        match tile.state {
+            TileState::Covered(Mark::Blank) | TileState::Covered(Mark::Question) => { }
+            _ => return None
         }
 
         tile.state = TileState::Uncovered;
 
-        if tile.kind == TileKind::Mine {
+        if tile.mines() > 0 {
             self.status = Status::Dead;
             Some(true)
         }
@@ -167,26 +639,33 @@ impl State {
         }
     }
 
-    pub fn dig(&mut self, ij: Coords) -> Vec<(Coords, Option<bool>)> {
-        if self.done() { return Vec::new(); }
-
-        let tile = &mut self.tiles[ij];
+    fn dig_tiles(&mut self, ij: Coords) -> Vec<(Coords, Option<bool>)> {
+        if !self.placed {
+            self.place(Some(ij));
+        }
 
         let mut results = Vec::new();
-        match tile.state {
-            TileState::Covered(false) => {
+        match self.tiles[ij].state {
+            TileState::Covered(Mark::Blank) | TileState::Covered(Mark::Question) => {
                 let boom = self.uncover(ij);
                 results.push((ij, boom));
             }
 
             TileState::Uncovered => {
-                for dj in -1 ..= 1 {
-                    for di in -1 ..= 1 {
-                        let adj = ij + V2::new(di, dj);
-                        if self.tiles.in_bounds(adj) {
-                            let boom = self.uncover(adj);
-                            results.push((adj, boom));
-                        }
+                let adjacent: Vec<Coords> = self.tiles.neighbours(ij).collect();
+                let n_flagged: usize = adjacent.iter()
+                    .map(|&adj| self.tiles[adj].flags())
+                    .sum();
+
+                let chord = match self.config.chording {
+                    Chording::Standard      => n_flagged == self.tiles[ij].n_near,
+                    Chording::Unconditional => true,
+                };
+
+                if chord {
+                    for adj in adjacent {
+                        let boom = self.uncover(adj);
+                        results.push((adj, boom));
                     }
                 }
             }
@@ -199,37 +678,266 @@ impl State {
         results
     }
 
+    // returns whether anything changed
+    fn flag_tile(&mut self, ij: Coords) -> bool {
+        let question = self.config.question_marks;
+        let can_flag = self.n_flags > 0 || self.config.flags == FlagLimit::Unlimited;
+
+        let most = self.config.mines_per_tile;
+
+        let changed = match &mut self.tiles[ij].state {
+            TileState::Covered(mark @ Mark::Blank) if can_flag => {
+                *mark = Mark::Flag(1);
+                self.n_flags -= 1;
+                true
+            }
+
+            // out of flags, so skip straight to the question mark
+            TileState::Covered(mark @ Mark::Blank) if question => {
+                *mark = Mark::Question;
+                true
+            }
+
+            // stack another, on tiles that can hold more than one mine
+            TileState::Covered(Mark::Flag(n)) if *n < most && can_flag => {
+                *n += 1;
+                self.n_flags -= 1;
+                true
+            }
+
+            TileState::Covered(mark @ Mark::Flag(_)) => {
+                if let Mark::Flag(n) = *mark {
+                    self.n_flags += n as isize;
+                }
+                *mark = if question { Mark::Question } else { Mark::Blank };
+                true
+            }
+
+            TileState::Covered(mark @ Mark::Question) => {
+                *mark = Mark::Blank;
+                true
+            }
+
+            _ => false
+        };
+
+        self.check_win();
+
+        changed
+    }
+
+    pub fn dig(&mut self, ij: Coords) -> Vec<(Coords, Option<bool>)> {
+        // nothing to dig in a gap in the outline
+        if self.done() || !self.tiles.contains(ij) { return Vec::new(); }
+
+        let before = self.snapshot();
+        let results = self.dig_tiles(ij);
+        if results.iter().any(|(_, boom)| boom.is_some()) {
+            self.remember(before);
+        }
+
+        self.update_timer(true);
+        results
+    }
+
     pub fn flag(&mut self, ij: Coords) {
-        if self.done() { return; }
+        // there's nothing to flag until the mines are down
+        if self.done() || !self.placed || !self.tiles.contains(ij) { return; }
+
+        let before = self.snapshot();
+        if self.flag_tile(ij) {
+            self.remember(before);
+        }
+
+        self.update_timer(true);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            placed:  self.placed,
+            tiles:   self.tiles.clone(),
+            status:  self.status,
+            n_flags: self.n_flags,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.placed  = snapshot.placed;
+        self.tiles   = snapshot.tiles;
+        self.status  = snapshot.status;
+        self.n_flags = snapshot.n_flags;
+    }
+
+    fn remember(&mut self, before: Snapshot) {
+        self.undo_stack.push(before);
+        self.redo_stack.clear();
+    }
 
-        if let TileState::Covered(flag) = &mut self.tiles[ij].state {
-            if !*flag && self.n_flags != 0 {
-                *flag = true;
-                self.n_flags -= 1
+    // returns whether there was anything to undo
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.redo_stack.push(current);
+                self.restore(snapshot);
+                self.undo_used = true;
+                self.update_timer(false);
+                true
             }
-            else {
-                *flag = false;
-                self.n_flags += 1;
+
+            None => false
+        }
+    }
+
+    // returns whether there was anything to redo
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(snapshot) => {
+                let current = self.snapshot();
+                self.undo_stack.push(current);
+                self.restore(snapshot);
+                self.update_timer(false);
+                true
             }
+
+            None => false
         }
+    }
 
-        self.check_win();
+    pub fn apply(&mut self, action: Action) {
+        match action {
+            Action::Dig(ij)  => { self.dig(ij); }
+            Action::Flag(ij) => self.flag(ij),
+            Action::Undo     => { self.undo(); }
+            Action::Redo     => { self.redo(); }
+        }
+    }
+
+    pub fn undo_used(&self) -> bool {
+        self.undo_used
+    }
+
+    // a tile the player can work out from what they can see: safe to dig if there is one,
+    // otherwise a mine to flag
+    pub fn hint(&mut self) -> Option<Coords> {
+        if self.done() || !self.placed { return None; }
+
+        let deductions = solver::deduce(self);
+        let hint = deductions.safe.first().or_else(|| deductions.mines.first()).copied();
+        if hint.is_some() {
+            self.hint_used = true;
+        }
+
+        hint
+    }
+
+    pub fn hint_used(&self) -> bool {
+        self.hint_used
+    }
+
+    // whether the layout is one no-guess generation settled for without proving it
+    pub fn unproven(&self) -> bool {
+        self.unproven
+    }
+
+    pub(crate) fn set_unproven(&mut self, unproven: bool) {
+        self.unproven = unproven;
+    }
+
+    // whether the player had help that rules the game out of the high scores
+    pub fn assisted(&self) -> bool {
+        self.undo_used || self.hint_used
+    }
+
+    // start the timer if the player has just acted, and run it only while the game is on
+    fn update_timer(&mut self, acted: bool) {
+        let now = self.clock.now();
+        let timer = &mut self.timer;
+        timer.started |= acted;
+
+        let running = timer.started && self.status == Status::Playing;
+        match (running, timer.since) {
+            (true, None) => timer.since = Some(now),
+            (false, Some(since)) => {
+                timer.banked += now.saturating_sub(since);
+                timer.since = None;
+            }
+            _ => { }
+        }
+    }
+
+    // milliseconds on the timer
+    pub fn elapsed(&self) -> u64 {
+        let running = self.timer.since
+            .map_or(0, |since| self.clock.now().saturating_sub(since));
+        self.timer.banked + running
+    }
+
+    pub fn timer_started(&self) -> bool {
+        self.timer.started
+    }
+
+    pub fn timer_running(&self) -> bool {
+        self.timer.since.is_some()
+    }
+
+    // time the game by a different clock from now on, keeping what's on the timer
+    pub fn set_clock(&mut self, clock: Rc<dyn Clock>) {
+        if self.timer.since.is_some() {
+            self.timer.banked = self.elapsed();
+            self.timer.since = Some(clock.now());
+        }
+
+        self.clock = clock;
     }
 
     fn check_win(&mut self) {
-        if !self.done() {
-            if self.tiles.iter()
-                .filter(|tile| match tile.kind {
-                    TileKind::Dirt => tile.state != TileState::Uncovered,
-                    TileKind::Mine => tile.state != TileState::Covered(true),
-                })
-                .count() == 0
-            {
-                self.status = Status::Won;
+        if self.done() || !self.cleared() {
+            return;
+        }
+
+        let won = match self.config.win {
+            WinRule::Flagged => self.tiles.iter()
+                .all(|tile| tile.mines() == tile.flags()),
+            WinRule::Cleared => true,
+        };
+
+        if won {
+            self.status = Status::Won;
+
+            // anything still covered is a mine, or a stack of them
+            for ij in self.tiles.indices().collect::<Vec<_>>() {
+                let mines = self.tiles[ij].mines();
+                if let TileState::Covered(mark) = &mut self.tiles[ij].state {
+                    *mark = Mark::Flag(mines);
+                }
             }
+            self.n_flags = 0;
         }
     }
 
+    fn cleared(&self) -> bool {
+        self.tiles.iter().all(|tile| tile.mines() > 0 || tile.state == TileState::Uncovered)
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // false until the first dig, when playing with a chosen opening
+    pub fn mines_placed(&self) -> bool {
+        self.placed
+    }
+
+    // the seed the mines are laid out from, whether given in the config or not
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tiles(&self) -> &Grid<Tile> {
+        &self.tiles
+    }
+
     pub fn enumerate_tiles<'a> (&'a self) -> impl Iterator<Item = (Coords, &'a Tile)> + 'a {
         self.tiles.enumerate()
     }
@@ -243,11 +951,18 @@ impl State {
     }
 
     pub fn restart(&mut self) {
-        let config = Config { seed: None, ..self.config };
+        self.restart_with_seed(None);
+    }
+
+    // a new game with the same config and clock
+    pub fn restart_with_seed(&mut self, seed: Option<u64>) {
+        let config = Config { seed, ..self.config.clone() };
+        let clock = self.clock.clone();
         *self = Self::new(config);
+        self.clock = clock;
     }
 
-    pub fn flags_remaining(&self) -> usize {
+    pub fn flags_remaining(&self) -> isize {
         self.n_flags
     }
 
@@ -256,3 +971,165 @@ impl State {
     }
 }
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // a plain board, with mines placed on the first dig
+    pub(crate) fn config(width: usize, height: usize, n_mines: usize, seed: u64) -> Config {
+        Config {
+            width,
+            height,
+            n_mines,
+            mines_per_tile: 1,
+            seed:       Some(seed),
+            generation: Generation::Random,
+            opening:    Opening::SafeTile,
+            chording:   Chording::Standard,
+            win:        WinRule::Cleared,
+            flags:      FlagLimit::Unlimited,
+            question_marks: false,
+            topology:   Topology::Flat,
+            tiling:     Tiling::Square,
+            neighbourhood: Neighbourhood::Adjacent,
+            outline:    Outline::Rectangle,
+        }
+    }
+
+    // play on through the public interface, doing only what the solver can prove
+    fn play_out(state: &mut State) {
+        while !state.done() {
+            let deductions = solver::deduce(state);
+            if deductions.is_empty() {
+                break;
+            }
+
+            for ij in deductions.mines {
+                state.flag(ij);
+            }
+            for ij in deductions.safe {
+                if state.tiles()[ij].state == TileState::Covered(Mark::Blank) {
+                    state.dig(ij);
+                }
+            }
+        }
+    }
+
+    fn no_guess(width: usize, height: usize, n_mines: usize, opening: Opening) {
+        for seed in 0 .. 5 {
+            let config = Config {
+                generation: Generation::NoGuess,
+                opening,
+                ..config(width, height, n_mines, seed)
+            };
+            let mut state = State::new(config);
+            if opening != Opening::Auto {
+                state.dig(Coords::new(width as i32 / 2, height as i32 / 2));
+            }
+
+            assert!(!state.unproven(), "seed {}", seed);
+            play_out(&mut state);
+            assert_eq!(state.status(), Status::Won, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn no_guess_clears_from_first_dig() {
+        no_guess(9, 9, 10, Opening::SafeTile);
+        no_guess(16, 16, 40, Opening::SafeArea);
+        no_guess(12, 9, 13, Opening::Auto);
+    }
+
+    #[test]
+    fn no_guess_dense() {
+        no_guess(9, 9, 25, Opening::SafeArea);
+    }
+
+    #[test]
+    fn no_guess_gives_up() {
+        // two mines among the three tiles around the first dig, and nothing to tell which
+        let mut state = State::new(Config {
+            generation: Generation::NoGuess,
+            ..config(2, 2, 2, 0)
+        });
+        state.dig(Coords::new(0, 0));
+
+        assert!(state.unproven());
+        assert_eq!(state.status(), Status::Playing);
+        assert_eq!(state.tiles().iter().map(Tile::mines).sum::<usize>(), 2);
+
+        // a random layout makes no promises to break
+        let mut state = State::new(config(2, 2, 2, 0));
+        state.dig(Coords::new(0, 0));
+        assert!(!state.unproven());
+    }
+
+    fn n_mines(state: &State) -> usize {
+        state.tiles().iter().map(Tile::mines).sum()
+    }
+
+    // mines placed by digging `start`
+    fn placed(config: Config, start: Coords) -> State {
+        let mut state = State::new(config);
+        assert!(!state.mines_placed());
+        state.dig(start);
+        assert!(state.mines_placed());
+        state
+    }
+
+    #[test]
+    fn placing_is_repeatable() {
+        let start = Coords::new(3, 4);
+        let layout = |seed| -> Vec<TileKind> {
+            let state = placed(config(9, 9, 10, seed), start);
+            state.tiles().iter().map(|tile| tile.kind).collect()
+        };
+
+        assert_eq!(layout(7), layout(7));
+        assert_ne!(layout(7), layout(8));
+    }
+
+    #[test]
+    fn safe_tile_and_area() {
+        let start = Coords::new(4, 4);
+        for seed in 0 .. 20 {
+            let state = placed(config(9, 9, 30, seed), start);
+            assert_eq!(state.tiles()[start].mines(), 0);
+            assert_eq!(n_mines(&state), 30);
+
+            let config = Config { opening: Opening::SafeArea, ..config(9, 9, 30, seed) };
+            let state = placed(config, start);
+            assert_eq!(state.tiles()[start].n_near, 0);
+            assert!(state.tiles().neighbours(start).all(|ij| state.tiles()[ij].mines() == 0));
+            assert_eq!(n_mines(&state), 30);
+        }
+    }
+
+    #[test]
+    fn safe_area_at_corner() {
+        // only four tiles around a corner to keep clear
+        let start = Coords::new(0, 0);
+        for seed in 0 .. 20 {
+            let config = Config { opening: Opening::SafeArea, ..config(5, 5, 20, seed) };
+            let state = placed(config, start);
+            assert_eq!(state.tiles().neighbours(start).count(), 3);
+            assert_eq!(state.tiles()[start].n_near, 0);
+            assert_eq!(n_mines(&state), 20);
+            assert_eq!(state.status(), Status::Playing);
+        }
+    }
+
+    #[test]
+    fn safe_area_too_crowded() {
+        // keeping the area clear would leave seven tiles for twelve mines, so only the tile dug
+        // is spared
+        let start = Coords::new(1, 1);
+        for seed in 0 .. 20 {
+            let config = Config { opening: Opening::SafeArea, ..config(4, 4, 12, seed) };
+            let state = placed(config, start);
+            assert_eq!(state.tiles()[start].mines(), 0);
+            assert_eq!(n_mines(&state), 12);
+            assert_eq!(state.status(), Status::Playing);
+        }
+    }
+}
@@ -1,14 +1,251 @@
 
 use {
-    ggez::nalgebra as na,
+    std::{fmt, rc::Rc, str::FromStr},
+    nalgebra as na,
 };
 
 pub type Coords = na::Point2<i32>;
 
+// which pairs of opposite edges are joined, making the tiles along one neighbours of those along
+// the other
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Wrap {
+    pub x: bool,
+    pub y: bool,
+}
+
+// how cells sit together, and so which touch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tiling {
+    // a cell touches the eight around it, corners included
+    Square,
+    // rows of hexagons, odd rows shifted half a cell right, each touching six
+    Hex,
+}
+
+impl Default for Tiling {
+    fn default() -> Tiling {
+        Tiling::Square
+    }
+}
+
+// the distance between the centres of hexagon rows, in cells across: √3/2
+const HEX_ROW: f32 = 0.866_025_4;
+// from a hexagon's centre to its top and bottom corners: 1/√3
+pub const HEX_RADIUS: f32 = 0.577_350_3;
+
+impl Tiling {
+    // steps to the cells touching one in row `y`
+    fn offsets(self, y: i32) -> &'static [(i32, i32)] {
+        const SQUARE: &[(i32, i32)] = &[
+            (-1, -1), (0, -1), (1, -1),
+            (-1,  0),          (1,  0),
+            (-1,  1), (0,  1), (1,  1),
+        ];
+        const HEX_EVEN: &[(i32, i32)] = &[(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+        const HEX_ODD:  &[(i32, i32)] = &[( 0, -1), (1, -1), (-1, 0), (1, 0), ( 0, 1), (1, 1)];
+
+        match self {
+            Tiling::Square                     => SQUARE,
+            Tiling::Hex if y.rem_euclid(2) == 0 => HEX_EVEN,
+            Tiling::Hex                        => HEX_ODD,
+        }
+    }
+
+    // where a cell's centre falls, measured in cells across from the board's top-left corner
+    pub fn center(self, p: Coords) -> (f32, f32) {
+        match self {
+            Tiling::Square => (p.x as f32 + 0.5, p.y as f32 + 0.5),
+            Tiling::Hex    => {
+                let shift = if p.y.rem_euclid(2) == 1 { 0.5 } else { 0. };
+                (p.x as f32 + 0.5 + shift, HEX_RADIUS + p.y as f32 * HEX_ROW)
+            }
+        }
+    }
+
+    // how much room a board takes, in cells across
+    pub fn extent(self, width: usize, height: usize) -> (f32, f32) {
+        match self {
+            Tiling::Square => (width as f32, height as f32),
+            Tiling::Hex    => {
+                let shift = if height > 1 { 0.5 } else { 0. };
+                (width as f32 + shift, 2. * HEX_RADIUS + (height as f32 - 1.) * HEX_ROW)
+            }
+        }
+    }
+
+    // the cell a point lies in, measured as for `center`. it may be off the board.
+    pub fn cell_at(self, x: f32, y: f32) -> Coords {
+        match self {
+            Tiling::Square => Coords::new(x.floor() as i32, y.floor() as i32),
+            // the hexagons are the points nearer one centre than any other, so try the rows either
+            // side and take the closest
+            Tiling::Hex => {
+                let row = ((y - HEX_RADIUS) / HEX_ROW).floor() as i32;
+                (row - 1 ..= row + 2)
+                    .map(|j| {
+                        let shift = if j.rem_euclid(2) == 1 { 0.5 } else { 0. };
+                        Coords::new((x - 0.5 - shift).round() as i32, j)
+                    })
+                    .min_by(|&a, &b| {
+                        let distance = |p: Coords| {
+                            let (cx, cy) = self.center(p);
+                            (cx - x).powi(2) + (cy - y).powi(2)
+                        };
+                        distance(a).partial_cmp(&distance(b)).unwrap()
+                    })
+                    .unwrap()
+            }
+        }
+    }
+}
+
+// which cells count towards a square cell's number. hexagons always count the six they touch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighbourhood {
+    // the eight touching it, corners included
+    Adjacent,
+    // only the four sharing an edge
+    Orthogonal,
+    // the eight a knight's move away
+    Knight,
+    // everything within two steps either way: the 5×5 square around it
+    Radius2,
+}
+
+impl Default for Neighbourhood {
+    fn default() -> Neighbourhood {
+        Neighbourhood::Adjacent
+    }
+}
+
+// the largest number any neighbourhood can show
+pub const MAX_NEIGHBOURS: usize = 24;
+
+impl Neighbourhood {
+    fn offsets(self) -> &'static [(i32, i32)] {
+        const ORTHOGONAL: &[(i32, i32)] = &[(0, -1), (-1, 0), (1, 0), (0, 1)];
+        const KNIGHT: &[(i32, i32)] = &[
+            (-1, -2), (1, -2), (-2, -1), (2, -1),
+            (-2,  1), (2,  1), (-1,  2), (1,  2),
+        ];
+        const RADIUS_2: &[(i32, i32)] = &[
+            (-2, -2), (-1, -2), (0, -2), (1, -2), (2, -2),
+            (-2, -1), (-1, -1), (0, -1), (1, -1), (2, -1),
+            (-2,  0), (-1,  0),          (1,  0), (2,  0),
+            (-2,  1), (-1,  1), (0,  1), (1,  1), (2,  1),
+            (-2,  2), (-1,  2), (0,  2), (1,  2), (2,  2),
+        ];
+
+        match self {
+            Neighbourhood::Adjacent   => Tiling::Square.offsets(0),
+            Neighbourhood::Orthogonal => ORTHOGONAL,
+            Neighbourhood::Knight     => KNIGHT,
+            Neighbourhood::Radius2    => RADIUS_2,
+        }
+    }
+}
+
+// everything besides the size that decides which cells are neighbours
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Shape {
+    pub tiling:        Tiling,
+    pub neighbourhood: Neighbourhood,
+    pub wrap:          Wrap,
+}
+
+impl Shape {
+    // steps to the neighbours of a cell in row `y`
+    fn offsets(self, y: i32) -> &'static [(i32, i32)] {
+        match self.tiling {
+            Tiling::Square => self.neighbourhood.offsets(),
+            Tiling::Hex    => self.tiling.offsets(y),
+        }
+    }
+}
+
+// which cells of a board hold tiles, for boards that aren't whole rectangles. it's written on one
+// line, a row at a time with `/` between, `#` for a cell and `.` for a gap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mask {
+    width:  usize,
+    height: usize,
+    cells:  Rc<[bool]>,
+}
+
+impl Mask {
+    pub fn new_generate(width: usize, height: usize, func: impl Fn(Coords) -> bool) -> Mask {
+        let cells = (0 .. height as i32)
+            .flat_map(|y| (0 .. width as i32).map(move |x| Coords::new(x, y)))
+            .map(func)
+            .collect();
+        Mask { width, height, cells }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, p: Coords) -> bool {
+        self.cells[self.width * p.y as usize + p.x as usize]
+    }
+
+    // how many cells there are, not counting the gaps
+    pub fn count(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell).count()
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (y, row) in self.cells.chunks(self.width).enumerate() {
+            if y > 0 { f.write_str("/")?; }
+            for &cell in row {
+                f.write_str(if cell { "#" } else { "." })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Mask {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Mask, String> {
+        let rows: Vec<&str> = s.split('/').collect();
+        let width = rows[0].len();
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return Err("mask rows must all be the same length".to_owned());
+        }
+
+        let mut cells = Vec::with_capacity(width * rows.len());
+        for ch in rows.iter().flat_map(|row| row.chars()) {
+            match ch {
+                '#' => cells.push(true),
+                '.' => cells.push(false),
+                _   => return Err(format!("unexpected '{}' in mask", ch)),
+            }
+        }
+
+        if !cells.contains(&true) {
+            return Err("mask has no cells".to_owned());
+        }
+
+        Ok(Mask { width, height: rows.len(), cells: cells.into() })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Grid<T> {
     vec:    Vec<T>,
     width:  usize,
     height: usize,
+    shape:  Shape,
+    // the cells left out, if any. there's still an element for each, which goes unused.
+    mask:   Option<Mask>,
 }
 
 impl<T> Grid<T> where T: Clone + Default {
@@ -33,15 +270,26 @@ impl<T> Grid<T> where T: Clone {
             })
             .collect();
 
-        Grid { vec, width, height }
+        Grid { vec, width, height, shape: Shape::default(), mask: None }
+    }
+
+    pub fn shaped(self, shape: Shape) -> Grid<T> {
+        Grid { shape, ..self }
+    }
+
+    // `mask` must be the same size as the grid
+    pub fn masked(self, mask: Option<Mask>) -> Grid<T> {
+        Grid { mask, ..self }
     }
 
+    // every cell, skipping gaps left by the mask
     pub fn indices<'a> (&'a self) -> impl Iterator<Item = Coords> + 'a {
         (0 .. self.height as i32)
             .flat_map(move |y|
                 (0 .. self.width as i32)
                     .map(move |x| Coords::new(x, y))
             )
+            .filter(move |&p| self.contains(p))
     }
 
     pub fn enumerate<'a> (&'a self) -> impl Iterator<Item = (Coords, &'a T)> + 'a {
@@ -49,7 +297,7 @@ impl<T> Grid<T> where T: Clone {
     }
 
     pub fn iter<'a> (&'a self) -> impl Iterator<Item = &'a T> + 'a {
-        self.vec.iter()
+        self.indices().map(move |ij| &self[ij])
     }
 
     pub fn width(&self) -> usize {
@@ -60,10 +308,46 @@ impl<T> Grid<T> where T: Clone {
         self.height
     }
 
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn mask(&self) -> Option<&Mask> {
+        self.mask.as_ref()
+    }
+
     pub fn in_bounds(&self, p: Coords) -> bool {
            p.x >= 0 && p.x < self.width  as i32
         && p.y >= 0 && p.y < self.height as i32
     }
+
+    // on the board, and not in a gap
+    pub fn contains(&self, p: Coords) -> bool {
+        self.in_bounds(p) && self.mask.as_ref().map_or(true, |mask| mask.contains(p))
+    }
+
+    // bring a point that's off the edge back round, if that edge wraps
+    pub fn wrapped(&self, p: Coords) -> Option<Coords> {
+        let wrap = self.shape.wrap;
+        let x = if wrap.x { p.x.rem_euclid(self.width  as i32) } else { p.x };
+        let y = if wrap.y { p.y.rem_euclid(self.height as i32) } else { p.y };
+        let q = Coords::new(x, y);
+        if self.contains(q) { Some(q) } else { None }
+    }
+
+    pub fn neighbours<'a> (&'a self, p: Coords) -> impl Iterator<Item = Coords> + 'a {
+        // on a board only a tile or two across, going round can come back to the same tile
+        let offsets = self.shape.offsets(p.y);
+        let mut adjacent: Vec<Coords> = Vec::with_capacity(offsets.len());
+        for &(dx, dy) in offsets {
+            match self.wrapped(p + na::Vector2::new(dx, dy)) {
+                Some(q) if q != p && !adjacent.contains(&q) => adjacent.push(q),
+                _ => { }
+            }
+        }
+
+        adjacent.into_iter()
+    }
 }
 
 impl<T> std::ops::Index<Coords> for Grid<T> {
@@ -0,0 +1,152 @@
+
+use {
+    crate::{
+        activity::*,
+        game,
+        maths::*,
+        records::{self, ROW_HEIGHT},
+        scores::{self, Board, Entry, Scores},
+        settings::Settings,
+    },
+    ggez::{
+        GameResult,
+        input::{
+            keyboard::{KeyCode, KeyMods},
+            mouse::MouseButton,
+        },
+    },
+};
+
+const WINDOW_WIDTH: f32 = 560.;
+
+// the title, a row per entry, then the way back
+const N_ROWS: usize = scores::TABLE_SIZE + 2;
+
+const FILE: &str = "scores";
+
+fn path(ctx: &ggez::Context) -> std::path::PathBuf {
+    ggez::filesystem::user_data_dir(ctx).join(FILE)
+}
+
+// enter a won game, returning its rank if it made the table
+pub fn add(ctx: &ggez::Context, settings: &Settings, state: &game::State) -> Option<usize> {
+    let date = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    records::update(&path(ctx), |scores: &mut Scores| {
+        scores.add(Board::played(state), Entry::new(state, date, &settings.name))
+    }).flatten()
+}
+
+pub struct HighScores {
+    board:    Board,
+    // `None` if the file couldn't be read
+    scores:   Option<Scores>,
+    // which of the two tables is showing
+    assisted: bool,
+    dirty:    bool,
+    switch:   Option<Switch>,
+}
+
+impl HighScores {
+    pub fn new(ctx: &ggez::Context, config: &game::Config) -> HighScores {
+        HighScores {
+            board:    Board::new(config),
+            scores:   records::try_load(&path(ctx)),
+            assisted: false,
+            dirty:    true,
+            switch:   None,
+        }
+    }
+
+    fn title(&self) -> String {
+        let table = if self.assisted { "with help" } else { "clean" };
+        format!("{}×{}, {} mines: {}", self.board.width, self.board.height, self.board.n_mines, table)
+    }
+}
+
+impl Activity for HighScores {
+    fn mouse_down<'a> (&mut self, _context: Context<'a>, button: MouseButton, position: P2) {
+        if button != MouseButton::Left { return; }
+
+        match (position.y / ROW_HEIGHT) as usize {
+            0                     => self.assisted = !self.assisted,
+            i if i == N_ROWS - 1  => self.switch = Some(Switch::Back),
+            _                     => return
+        }
+
+        self.dirty = true;
+    }
+
+    fn mouse_up<'a> (&mut self, _context: Context<'a>, _button: MouseButton, _position: P2) {
+    }
+
+    fn mouse_move<'a> (&mut self, _context: Context<'a>, _position: P2) {
+    }
+
+    fn resized<'a> (&mut self, _context: Context<'a>, _width: f32, _height: f32) {
+    }
+
+    fn key_down<'a> (&mut self, _context: Context<'a>, key: KeyCode, _mods: KeyMods) {
+        match key {
+            KeyCode::Left | KeyCode::Right | KeyCode::Tab => self.assisted = !self.assisted,
+            KeyCode::Escape | KeyCode::Return             => self.switch = Some(Switch::Back),
+            _                                             => return
+        }
+
+        self.dirty = true;
+    }
+
+    fn draw<'a> (
+        &mut self,
+        Context { ctx, assets, settings, .. }: Context<'a>)
+        -> GameResult
+    {
+        use ggez::graphics::{Align, draw, DrawParam};
+
+        records::draw_frame(ctx, assets, settings, WINDOW_WIDTH, N_ROWS, self.title())?;
+        let label = |text: String, align: Align| records::label(assets, WINDOW_WIDTH, text, align);
+
+        let entries = match &self.scores {
+            Some(scores) => scores.table(&self.board, self.assisted),
+            None         => &[],
+        };
+        for (rank, entry) in entries.iter().enumerate() {
+            let dest = records::row_dest(rank + 1);
+            let time = format!(
+                "{:2}. {:7.3}s  {:5.2} 3BV/s  {}",
+                rank + 1, entry.time as f64 * 0.001, entry.rate(), entry.name);
+            draw(ctx, &label(time, Align::Left), DrawParam::new().dest(dest))?;
+            draw(ctx, &label(scores::format_date(entry.date), Align::Right), DrawParam::new().dest(dest))?;
+        }
+
+        if entries.is_empty() {
+            let none = match self.scores {
+                Some(_) => "no wins yet",
+                None    => "couldn't read the high scores file",
+            };
+            draw(ctx, &label(none.to_owned(), Align::Center), DrawParam::new().dest(records::row_dest(1)))?;
+        }
+
+        let back = label("back".to_owned(), Align::Center);
+        draw(ctx, &back, DrawParam::new().dest(records::row_dest(N_ROWS - 1)))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn close<'a> (&mut self, _context: Context<'a>) {
+    }
+
+    fn focus<'a> (&mut self, _context: Context<'a>, _focused: bool) {
+    }
+
+    fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn switch(&mut self) -> Option<Switch> {
+        self.switch.take()
+    }
+}
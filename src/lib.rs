@@ -0,0 +1,13 @@
+
+// the game itself, free of windowing and graphics, so that it can be played by anything
+
+pub mod clock;
+pub mod format;
+pub mod game;
+pub mod grid;
+pub mod metrics;
+pub mod replay;
+pub mod save;
+pub mod scores;
+pub mod solver;
+pub mod stats;
@@ -4,18 +4,30 @@
 mod activity;
 mod animator;
 mod assets;
+mod bindings;
 mod configure;
-mod game;
-mod grid;
+mod highscores;
 mod play;
+mod playback;
+mod records;
+mod settings;
+mod statistics;
+
+// the core lives in the library; these make it reachable as `crate::game` and so on
+use mines::{clock, format, game, grid, metrics, replay, save, scores, stats};
 
 use {
     crate::{
-        activity::{Activity, Context},
+        activity::{Activity, Context, Switch},
         animator::Animator,
         assets::Assets,
         configure::Configure,
+        highscores::HighScores,
         play::Play,
+        playback::Playback,
+        replay::Replay,
+        settings::Settings,
+        statistics::Statistics,
     },
     std::rc::Rc,
     ggez::{
@@ -116,28 +128,48 @@ impl Animator for Animations {
 enum SomeActivity {
     Play(Play),
     Configure(Configure),
+    Playback(Playback),
+    HighScores(HighScores),
+    Statistics(Statistics),
 }
 
 impl SomeActivity {
     fn inner<'a> (&'a self) -> &'a dyn Activity {
         match self {
-            SomeActivity::Play(p)      => p,
-            SomeActivity::Configure(c) => c,
+            SomeActivity::Play(p)       => p,
+            SomeActivity::Configure(c)  => c,
+            SomeActivity::Playback(p)   => p,
+            SomeActivity::HighScores(h) => h,
+            SomeActivity::Statistics(s) => s,
         }
     }
 
     fn inner_mut<'a> (&'a mut self) -> &'a mut dyn Activity {
         match self {
-            SomeActivity::Play(p)      => p,
-            SomeActivity::Configure(c) => c,
+            SomeActivity::Play(p)       => p,
+            SomeActivity::Configure(c)  => c,
+            SomeActivity::Playback(p)   => p,
+            SomeActivity::HighScores(h) => h,
+            SomeActivity::Statistics(s) => s,
         }
     }
 }
 
+// where the mouse is, in the screen coordinates the activities draw in
+fn screen_position(ctx: &ggez::Context) -> maths::P2 {
+    let screen = graphics::screen_coordinates(ctx);
+    let (width, height) = graphics::drawable_size(ctx);
+    let position = ggez::input::mouse::position(ctx);
+    maths::P2::new(
+        screen.x + position.x * screen.w / width,
+        screen.y + position.y * screen.h / height)
+}
+
 struct App {
     ctx: ggez::Context,
 
     assets: Assets,
+    settings: Settings,
 
     // Render state
     animations: Animations,
@@ -145,20 +177,24 @@ struct App {
 
     // App state
     activity: SomeActivity,
+    // left behind while configuring, to go back to
+    paused:   Option<SomeActivity>,
 }
 
 impl App {
-    fn new(mut ctx: ggez::Context) -> GameResult<App> {
-        let assets = Assets::load(&mut ctx)?;
+    fn new(mut ctx: ggez::Context, settings: Settings, activity: SomeActivity) -> GameResult<App> {
+        let assets = Assets::load(&mut ctx, play::TILE_SIZE)?;
 
         let app = App {
             ctx,
             assets,
+            settings,
 
             animations: Animations::new(),
             dirty: true,
 
-            activity: SomeActivity::Play(Play::new()),
+            activity,
+            paused: None,
         };
 
         Ok(app)
@@ -167,8 +203,9 @@ impl App {
     fn draw(&mut self) -> GameResult {
         let context = Context {
             ctx:      &mut self.ctx,
-            assets:   &self.assets,
+            assets:   &mut self.assets,
             animator: &mut self.animations,
+            settings: &self.settings,
         };
 
         self.activity.inner_mut().draw(context)?;
@@ -190,15 +227,38 @@ impl App {
         use ggez::event::winit_event::{ElementState, Event::*, WindowEvent::*};
         match event {
             WindowEvent { event, .. } => match event {
-                CloseRequested => ggez::event::quit(&mut self.ctx),
+                CloseRequested => {
+                    let context = Context {
+                        ctx:      &mut self.ctx,
+                        assets:   &mut self.assets,
+                        animator: &mut self.animations,
+                        settings: &self.settings,
+                    };
+
+                    self.activity.inner_mut().close(context);
+
+                    if let Some(mut paused) = self.paused.take() {
+                        let context = Context {
+                            ctx:      &mut self.ctx,
+                            assets:   &mut self.assets,
+                            animator: &mut self.animations,
+                            settings: &self.settings,
+                        };
+
+                        paused.inner_mut().close(context);
+                    }
+
+                    ggez::event::quit(&mut self.ctx);
+                }
 
                 MouseInput { state, button, .. } => {
-                    let pos = ggez::input::mouse::position(&mut self.ctx).into();
+                    let pos = screen_position(&self.ctx);
                     let act = self.activity.inner_mut();
                     let context = Context {
                         ctx:      &mut self.ctx,
-                        assets:   &self.assets,
+                        assets:   &mut self.assets,
                         animator: &mut self.animations,
+                        settings: &self.settings,
                     };
 
                     match state {
@@ -207,12 +267,141 @@ impl App {
                     }
                 }
 
+                CursorMoved { .. } => {
+                    let pos = screen_position(&self.ctx);
+                    let context = Context {
+                        ctx:      &mut self.ctx,
+                        assets:   &mut self.assets,
+                        animator: &mut self.animations,
+                        settings: &self.settings,
+                    };
+
+                    self.activity.inner_mut().mouse_move(context, pos);
+                }
+
+                KeyboardInput { input, .. } => {
+                    let key = match (input.state, input.virtual_keycode) {
+                        (ElementState::Pressed, Some(key)) => key,
+                        _                                  => return
+                    };
+
+                    let mods = ggez::input::keyboard::active_mods(&self.ctx);
+                    let act = self.activity.inner_mut();
+                    let context = Context {
+                        ctx:      &mut self.ctx,
+                        assets:   &mut self.assets,
+                        animator: &mut self.animations,
+                        settings: &self.settings,
+                    };
+
+                    act.key_down(context, key, mods);
+                }
+
+                Focused(focused) => {
+                    let context = Context {
+                        ctx:      &mut self.ctx,
+                        assets:   &mut self.assets,
+                        animator: &mut self.animations,
+                        settings: &self.settings,
+                    };
+
+                    self.activity.inner_mut().focus(context, focused);
+                }
+
+                Resized(size) => {
+                    let context = Context {
+                        ctx:      &mut self.ctx,
+                        assets:   &mut self.assets,
+                        animator: &mut self.animations,
+                        settings: &self.settings,
+                    };
+
+                    self.activity.inner_mut().resized(context, size.width as f32, size.height as f32);
+
+                    // they were placed for the old layout
+                    self.animations.clear_animations();
+                    self.dirty = true;
+                }
+
                 Refresh => self.dirty = true,
 
                 _ => { }
             },
             _ => { }
         }
+
+        self.switch();
+    }
+
+    // leave the game waiting, out of focus, while something else is up
+    fn visit(&mut self, activity: SomeActivity) {
+        if self.paused.is_some() {
+            self.activity = activity;
+            return;
+        }
+
+        let context = Context {
+            ctx:      &mut self.ctx,
+            assets:   &mut self.assets,
+            animator: &mut self.animations,
+            settings: &self.settings,
+        };
+        self.activity.inner_mut().focus(context, false);
+
+        self.paused = Some(std::mem::replace(&mut self.activity, activity));
+    }
+
+    fn switch(&mut self) {
+        let switch = match self.activity.inner_mut().switch() {
+            Some(switch) => switch,
+            None         => return
+        };
+
+        match switch {
+            Switch::Configure(config) => {
+                self.visit(SomeActivity::Configure(Configure::new(&self.ctx, config)));
+            }
+
+            Switch::Scores(config) => {
+                self.visit(SomeActivity::HighScores(HighScores::new(&self.ctx, &config)));
+            }
+
+            Switch::Stats(config) => {
+                self.visit(SomeActivity::Statistics(Statistics::new(&self.ctx, &config)));
+            }
+
+            Switch::Play(config) => {
+                // the chosen config becomes the one to start with next time
+                self.settings.config = config.clone();
+                settings::save(&self.ctx, &self.settings);
+
+                self.activity = match self.paused.take() {
+                    Some(SomeActivity::Play(mut play)) => {
+                        play.reconfigure(&self.ctx, config);
+                        SomeActivity::Play(play)
+                    }
+
+                    _ => SomeActivity::Play(Play::fresh(config))
+                };
+            }
+
+            Switch::Back => {
+                if let Some(paused) = self.paused.take() {
+                    self.activity = paused;
+
+                    let context = Context {
+                        ctx:      &mut self.ctx,
+                        assets:   &mut self.assets,
+                        animator: &mut self.animations,
+                        settings: &self.settings,
+                    };
+                    self.activity.inner_mut().focus(context, true);
+                }
+            }
+        }
+
+        self.animations.clear_animations();
+        self.dirty = true;
     }
 
     fn run(&mut self, mut event_loop: ggez::event::EventsLoop) -> GameResult {
@@ -235,10 +424,19 @@ impl App {
     }
 }
 
+fn load_replay(path: &std::ffi::OsStr) -> Result<Replay, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(path)?;
+    let replay = Replay::read(std::io::BufReader::new(file))?;
+    Ok(replay)
+}
+
 fn main() -> GameResult {
+    // only until the settings are loaded and the first activity fits the window to itself
+    let (width, height) = play::window_size(&Settings::default().config, play::TILE_SIZE);
+
     let window_mode = ggez::conf::WindowMode {
-        width:  play::WINDOW_WIDTH,
-        height: play::WINDOW_HEIGHT,
+        width,
+        height,
         maximized: false,
         fullscreen_type: ggez::conf::FullscreenType::Windowed,
         borderless: false,
@@ -246,7 +444,7 @@ fn main() -> GameResult {
         max_width:  0.,
         min_height: 0.,
         max_height: 0.,
-        resizable: false,
+        resizable: true,
     };
 
     let window_setup = ggez::conf::WindowSetup {
@@ -263,7 +461,22 @@ fn main() -> GameResult {
         .window_setup(window_setup)
         .build()?;
 
-    let app = &mut App::new(ctx)?;
+    let settings = settings::load(&ctx);
+
+    // a replay file given on the command line is played back instead of starting a game
+    let activity = match std::env::args_os().nth(1) {
+        Some(path) => match load_replay(&path) {
+            Ok(replay) => SomeActivity::Playback(Playback::new(replay)),
+            Err(err) => {
+                eprintln!("couldn't load replay {}: {}", path.to_string_lossy(), err);
+                SomeActivity::Play(Play::new(&ctx, settings.config.clone()))
+            }
+        },
+
+        None => SomeActivity::Play(Play::new(&ctx, settings.config.clone()))
+    };
+
+    let app = &mut App::new(ctx, settings, activity)?;
     app.run(event_loop)
 }
 
@@ -0,0 +1,174 @@
+
+// measures of how much work a board takes to clear, so that games on different boards can be
+// compared. they only make sense once the mines are down.
+
+use crate::{
+    game::{Tile, TileKind},
+    grid::{Coords, Grid},
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Metrics {
+    // the fewest clicks that clear the board: one per opening, and one per number outside them
+    pub bbbv:     usize,
+    // connected regions of empty tiles, each cleared by a single click
+    pub openings: usize,
+    // connected groups of numbers that no opening reaches
+    pub islands:  usize,
+    // tiles that aren't mines
+    pub safe:     usize,
+}
+
+fn is_empty(tile: &Tile) -> bool {
+    tile.kind == TileKind::Dirt && tile.n_near == 0
+}
+
+// mark everything connected to `start` that `within` accepts, and return it
+fn fill(
+    tiles:  &Grid<Tile>,
+    seen:   &mut Grid<bool>,
+    start:  Coords,
+    within: impl Fn(&Tile) -> bool)
+    -> Vec<Coords>
+{
+    let mut region = vec![start];
+    seen[start] = true;
+    let mut i = 0;
+    while let Some(&p) = region.get(i) {
+        for adj in tiles.neighbours(p) {
+            if !seen[adj] && within(&tiles[adj]) {
+                seen[adj] = true;
+                region.push(adj);
+            }
+        }
+        i += 1;
+    }
+
+    region
+}
+
+pub fn measure(tiles: &Grid<Tile>) -> Metrics {
+    let mut metrics = Metrics::default();
+    let indices: Vec<Coords> = tiles.indices().collect();
+
+    // openings, and the numbers around their edges that they clear along with them
+    let mut in_opening = Grid::new_fill(tiles.width(), tiles.height(), false);
+    let mut cleared = in_opening.clone();
+    for &ij in &indices {
+        if in_opening[ij] || !is_empty(&tiles[ij]) { continue; }
+
+        metrics.openings += 1;
+        for p in fill(tiles, &mut in_opening, ij, is_empty) {
+            cleared[p] = true;
+            for adj in tiles.neighbours(p) {
+                cleared[adj] = true;
+            }
+        }
+    }
+
+    // everything left takes a click of its own
+    let mut seen = cleared.clone();
+    for &ij in &indices {
+        if tiles[ij].mines() > 0 { continue; }
+
+        metrics.safe += 1;
+        if seen[ij] { continue; }
+
+        metrics.islands += 1;
+        fill(tiles, &mut seen, ij, |tile| tile.kind == TileKind::Dirt);
+    }
+
+    let unopened = indices.iter()
+        .filter(|&&ij| tiles[ij].kind == TileKind::Dirt && !cleared[ij])
+        .count();
+    metrics.bbbv = metrics.openings + unopened;
+
+    metrics
+}
+
+// 3BV per second, for a board cleared in `time` milliseconds
+pub fn rate(bbbv: usize, time: u64) -> f64 {
+    bbbv as f64 * 1000. / time.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            game::{Mark, TileState},
+            grid::{Mask, Shape, Wrap},
+        },
+    };
+
+    // '*' a mine, '.' a safe tile, '-' a gap in the outline
+    fn board(rows: &[&str], wrap: Wrap) -> Grid<Tile> {
+        let mask: Option<Mask> = if rows.iter().any(|row| row.contains('-')) {
+            let cells: String = rows.join("/").chars()
+                .map(|ch| match ch { '-' => '.', '/' => '/', _ => '#' })
+                .collect();
+            Some(cells.parse().unwrap())
+        }
+        else {
+            None
+        };
+
+        let rows: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        let mut tiles = Grid::new_generate(rows[0].len(), rows.len(), |ij| {
+            let kind = match rows[ij.y as usize][ij.x as usize] {
+                '*' => TileKind::Mine(1),
+                _   => TileKind::Dirt,
+            };
+            Tile { kind, state: TileState::Covered(Mark::Blank), n_near: 0 }
+        })
+            .shaped(Shape { wrap, ..Shape::default() })
+            .masked(mask);
+
+        let indices: Vec<Coords> = tiles.indices().collect();
+        for ij in indices {
+            tiles[ij].n_near = tiles.neighbours(ij).map(|adj| tiles[adj].mines()).sum();
+        }
+        tiles
+    }
+
+    #[test]
+    fn openings_and_numbers() {
+        // two openings, which between them clear all but the three numbers bottom right
+        let tiles = board(&[
+            "*....",
+            ".....",
+            "...*.",
+            ".....",
+        ], Wrap::default());
+        assert_eq!(measure(&tiles), Metrics { bbbv: 5, openings: 2, islands: 1, safe: 18 });
+    }
+
+    #[test]
+    fn islands_only() {
+        // four numbers touching corner to corner: one island, but a click for each
+        let tiles = board(&[
+            "*.*",
+            ".*.",
+            "*.*",
+        ], Wrap::default());
+        assert_eq!(measure(&tiles), Metrics { bbbv: 4, openings: 0, islands: 1, safe: 4 });
+    }
+
+    #[test]
+    fn masked_and_wrapped() {
+        let rows = [
+            "..-..",
+            "..-..",
+            "..-.*",
+        ];
+
+        // the gap splits the board into an opening each side, and isn't counted. the number
+        // left of the mine isn't next to either.
+        let flat = board(&rows, Wrap::default());
+        assert_eq!(measure(&flat), Metrics { bbbv: 3, openings: 2, islands: 1, safe: 11 });
+
+        // going round the side joins the two
+        let wrapped = board(&rows, Wrap { x: true, y: false });
+        assert_eq!(measure(&wrapped), Metrics { bbbv: 2, openings: 1, islands: 1, safe: 11 });
+    }
+}
@@ -3,164 +3,692 @@ use {
     super::maths::*,
     crate::{
         activity::*,
+        assets::Assets,
+        clock::{Clock, WallClock},
         game,
+        grid::{Coords, Tiling},
+        highscores,
+        metrics,
         animator::SimpleKey,
+        replay::{Event, Replay},
+        save,
+        settings::{Settings, Theme},
+        statistics,
     },
+    std::{io::Write, rc::Rc},
     ggez::{
         GameResult,
-        input::mouse::MouseButton,
+        input::{
+            keyboard::{KeyCode, KeyMods},
+            mouse::MouseButton,
+        },
     },
 };
 
-const GRID_WIDTH:  usize = 12;
-const GRID_HEIGHT: usize = (GRID_WIDTH / 4) * 3;
-const GRID_AREA:   usize = GRID_WIDTH * GRID_HEIGHT;
-const N_MINES:     usize = GRID_AREA / 8;
-
-// TODO un-pub these
+// the size of tiles in a new window, before any scaling, and the size everything else is laid out
+// for. the board's tiles grow and shrink with the window.
 pub const TILE_SIZE:  f32 = 30.;
 
-const BAR_SCALE:  f32 = 1.5;
+pub const BAR_SCALE:  f32 = 1.5;
 pub const BAR_HEIGHT: f32 = TILE_SIZE * BAR_SCALE;
 
-const TONE_FOREGROUND: f32 = 0.50;
-const TONE_BACKGROUND: f32 = 0.07;
+// any smaller and there's no telling the digits apart
+const MIN_TILE_SIZE: f32 = 8.;
+
+pub fn window_size(config: &game::Config, tile: f32) -> (f32, f32) {
+    let (w, h) = config.tiling.extent(config.width, config.height);
+    (w * tile, (h + BAR_SCALE) * tile)
+}
+
+// where things go in a window of a given size: the bar across the top, and beneath it the board,
+// with tiles as big as will fit and centred in the space left
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub tile:   f32,
+    pub bar:    f32,
+    pub width:  f32,
+    // the board's top-left corner
+    pub origin: P2,
+    pub tiling: Tiling,
+}
+
+impl Layout {
+    pub fn fit(config: &game::Config, width: f32, height: f32) -> Layout {
+        let (w, h) = config.tiling.extent(config.width, config.height);
+        // whole pixels, so that the gaps between tiles are all alike
+        let tile = (width / w).min(height / (h + BAR_SCALE)).floor().max(MIN_TILE_SIZE);
+        let bar = tile * BAR_SCALE;
+        let origin = P2::new(
+            ((width - w * tile) * 0.5).floor(),
+            bar + ((height - bar - h * tile) * 0.5).floor());
+        Layout { tile, bar, width, origin, tiling: config.tiling }
+    }
+
+    // the top-left corner of a tile-sized square around the tile's centre, where its mesh and
+    // glyphs are drawn
+    pub fn tile_position(&self, ij: Coords) -> P2 {
+        self.tile_center(ij) - V2::new(0.5, 0.5) * self.tile
+    }
+
+    pub fn tile_center(&self, ij: Coords) -> P2 {
+        let (x, y) = self.tiling.center(ij);
+        self.origin + V2::new(x, y) * self.tile
+    }
+
+    // the tile under a point on the screen, which may be off the board
+    pub fn tile_at(&self, position: P2) -> Coords {
+        let p = (position - self.origin) / self.tile;
+        self.tiling.cell_at(p.x, p.y)
+    }
+}
+
+// show the window at `width` by `height`, with the screen in window pixels, and lay it out for
+// `config`'s board
+pub fn fit_window(ctx: &mut ggez::Context, config: &game::Config, width: f32, height: f32)
+    -> GameResult<Layout>
+{
+    resize_window(ctx, 1., width, height)?;
+    Ok(Layout::fit(config, width, height))
+}
+
+const SAVE_FILE: &str = "game.save";
+
+fn save_path(ctx: &ggez::Context) -> std::path::PathBuf {
+    ggez::filesystem::user_data_dir(ctx).join(SAVE_FILE)
+}
+
+// pick up the game left unfinished last time, if any. the save is used up either way.
+fn resume(ctx: &ggez::Context) -> Option<(game::State, Replay, String)> {
+    let path = save_path(ctx);
+    let file = std::fs::File::open(&path).ok()?;
+    let result = save::read(std::io::BufReader::new(file));
+    if let Err(err) = std::fs::remove_file(&path) {
+        eprintln!("couldn't remove {}: {}", path.display(), err);
+    }
+
+    match result {
+        Ok(resumed) => Some(resumed),
+        Err(err) => {
+            eprintln!("discarding saved game {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+// what the statistics count as clicks: digs and flags, but not undos and redos
+fn is_click(event: Event) -> bool {
+    matches!(event, Event::Action(game::Action::Dig(_)) | Event::Action(game::Action::Flag(_)))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pressing {
+    Dig,
+    Flag,
+    Chord,
+}
 
-pub const WINDOW_WIDTH:  f32 = GRID_WIDTH  as f32 * TILE_SIZE;
-pub const WINDOW_HEIGHT: f32 = GRID_HEIGHT as f32 * TILE_SIZE + BAR_HEIGHT;
+// buttons held down over the board, which act once one is let go
+#[derive(Clone, Copy)]
+struct Press {
+    button:   MouseButton,
+    pressing: Pressing,
+    // None while the mouse is off the board
+    at:       Option<Coords>,
+}
 
 pub struct Play {
     state: game::State,
     boop:  bool,
     dirty: bool,
+
+    // the window size, once the player has chosen one
+    size:        Option<(f32, f32)>,
+    layout:      Layout,
+
+    replay:      Replay,
+    replay_name: String,
+    // runs the timer and stamps the replay; stopped while the window is out of focus
+    clock:       Rc<WallClock>,
+    // whether the current game's win has gone into the high scores
+    scored:      bool,
+    // the mouse buttons down, wherever they went down, and what they'll do over the board
+    held:        Vec<MouseButton>,
+    press:       Option<Press>,
+    // the tile the keyboard acts on, once a key has brought it up
+    cursor:      Option<Coords>,
+    // digs and flags in the current game, and whether it's gone into the statistics
+    clicks:      usize,
+    counted:     bool,
+
+    switch: Option<Switch>,
 }
 
 impl Play {
-    pub fn new() -> Play {
-        let config = game::Config {
-            width:   GRID_WIDTH,
-            height:  GRID_HEIGHT,
-            n_mines: N_MINES,
-            seed:    None
-        };
+    // resume the game left unfinished last time, or start one with `config`
+    pub fn new(ctx: &ggez::Context, config: game::Config) -> Play {
+        match resume(ctx) {
+            Some((state, replay, replay_name)) => Play::with_state(state, replay, replay_name),
+            None                               => Play::fresh(config),
+        }
+    }
 
+    pub fn fresh(config: game::Config) -> Play {
         let state = game::State::new(config);
+        let replay = Replay::new(&state);
+        // named for when it was started
+        let replay_name = format!(
+            "{}.replay",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()));
+        Play::with_state(state, replay, replay_name)
+    }
+
+    fn with_state(mut state: game::State, replay: Replay, replay_name: String) -> Play {
+        // carry on the replay's clock from where it left off
+        let clock = Rc::new(WallClock::new(replay.duration()));
+        state.set_clock(clock.clone());
+
+        // the clicks since the last restart belong to the game being resumed
+        let clicks = replay.events().iter().rev()
+            .take_while(|(_, event)| !matches!(event, Event::Restart(_)))
+            .filter(|&&(_, event)| is_click(event))
+            .count();
+
+        let (width, height) = window_size(state.config(), TILE_SIZE);
+        let layout = Layout::fit(state.config(), width, height);
+
+        Play {
+            state,
+            boop: false,
+            dirty: true,
+            size: None,
+            layout,
+            replay,
+            replay_name,
+            clock,
+            scored: false,
+            held: Vec::new(),
+            press: None,
+            cursor: None,
+            clicks,
+            counted: false,
+            switch: None,
+        }
+    }
+
+    // give up on the game in progress and start over with a different config
+    pub fn reconfigure(&mut self, ctx: &ggez::Context, config: game::Config) {
+        self.abandon(ctx);
+        self.save_replay(ctx);
+        *self = Play::fresh(config);
+    }
+
+    fn save_game(&self, ctx: &ggez::Context) {
+        let path = save_path(ctx);
+        let result = std::fs::create_dir_all(ggez::filesystem::user_data_dir(ctx))
+            .and_then(|_| std::fs::File::create(&path))
+            .and_then(|file| {
+                let mut w = std::io::BufWriter::new(file);
+                save::write(&mut w, &self.state, &self.replay, &self.replay_name)?;
+                w.flush()
+            });
+
+        if let Err(err) = result {
+            eprintln!("couldn't save game to {}: {}", path.display(), err);
+        }
+    }
+
+    // a game given up on part way through still counts as played
+    fn abandon(&mut self, ctx: &ggez::Context) {
+        if !self.counted && self.clicks > 0 {
+            statistics::add(ctx, &self.state, self.clicks);
+            self.counted = true;
+        }
+    }
+
+    // dig or flag a tile, by mouse or from the cursor
+    fn act(
+        &mut self,
+        Context { ctx, assets, animator, settings }: Context,
+        action: game::Action)
+    {
+        let layout = self.layout;
+        let was_done = self.state.done();
+
+        match action {
+            game::Action::Dig(ij) => {
+                let dug = self.state.dig(ij);
+                for (ij, boom) in dug.iter().copied() {
+                    let boom = match boom {
+                        Some(boom) => boom,
+                        None       => continue
+                    };
+
+                    let center = layout.tile_center(ij);
+
+                    let key = SimpleKey::new()
+                        .position(center);
+
+                    if boom {
+                        animator.animate_simple(
+                            2.0,
+                            assets.circle.clone(),
+                            key.color(V4::new(1.0, 1.0, 0.0, 0.5)),
+                            key.color(V4::new(0.3, 0.0, 0.0, 0.0))
+                                .scale(V2::repeat(layout.tile * 30.)),
+                        );
+                    }
+                    else {
+                        animator.animate_simple(
+                            0.2,
+                            assets.circle.clone(),
+                            key.color(V4::new(1.0, 1.0, 1.0, 0.5)),
+                            key.color(V4::new(0.0, 0.5, 1.0, 0.0))
+                                .scale(V2::repeat(layout.tile * 1.2)),
+                        );
+                    }
+                }
+
+                // a refused chord: show which tiles it would have opened
+                let tiles = self.state.tiles();
+                if dug.is_empty() && tiles[ij].state == game::TileState::Uncovered {
+                    for adj in tiles.neighbours(ij) {
+                        match tiles[adj].state {
+                            game::TileState::Covered(game::Mark::Flag(_)) => continue,
+                            game::TileState::Covered(_)                   => { }
+                            game::TileState::Uncovered                    => continue,
+                        }
+
+                        let center = layout.tile_center(adj);
+
+                        let key = SimpleKey::new()
+                            .position(center);
+
+                        animator.animate_simple(
+                            0.15,
+                            assets.circle.clone(),
+                            key.color(V4::new(0.0, 0.0, 0.0, 0.5))
+                                .scale(V2::repeat(layout.tile * 0.5)),
+                            key.color(V4::new(0.0, 0.0, 0.0, 0.0))
+                                .scale(V2::repeat(layout.tile * 0.3)),
+                        );
+                    }
+                }
+            }
 
-        Play { state, boop: false, dirty: true }
+            game::Action::Flag(ij) => {
+                self.state.flag(ij);
+            }
+
+            _ => { }
+        }
+
+        if !was_done {
+            self.record(ctx, settings, Event::Action(action));
+        }
+
+        if self.state.status() == game::Status::Won {
+            let bar_center = P2::new(layout.width * 0.5, layout.bar * 0.5);
+
+            let key = SimpleKey::new()
+                .position(bar_center);
+
+            animator.animate_simple(
+                1.5,
+                assets.star.clone(),
+                key.color(V4::new(1.0, 1.0, 1.0, 1.)),
+                key.color(V4::new(1.0, 0.4, 0.7, 0.))
+                    .scale(V2::repeat(layout.width * 1.5))
+                    .angle(10.),
+            );
+        }
+
+        self.dirty = true;
+    }
+
+    // the tile under a point on the screen, if there is one
+    fn tile_at(&self, position: P2) -> Option<Coords> {
+        let ij = self.layout.tile_at(position);
+        if self.state.tiles().contains(ij) { Some(ij) } else { None }
+    }
+
+    // the covered tiles a held button would dig: the one under it, or for a chord those around it
+    fn pressed_tiles(&self) -> Vec<Coords> {
+        let (pressing, ij) = match self.press {
+            Some(Press { pressing, at: Some(ij), .. }) => (pressing, ij),
+            _                                          => return Vec::new(),
+        };
+
+        let tiles = self.state.tiles();
+        let around: Vec<Coords> = match (pressing, tiles[ij].state) {
+            (Pressing::Flag, _)                           => return vec![ij],
+            (_, game::TileState::Uncovered)               => tiles.neighbours(ij).collect(),
+            (Pressing::Chord, game::TileState::Covered(_)) => Vec::new(),
+            (Pressing::Dig, game::TileState::Covered(_))   => vec![ij],
+        };
+
+        around.into_iter()
+            .filter(|&p| match tiles[p].state {
+                game::TileState::Covered(mark) => !matches!(mark, game::Mark::Flag(_)),
+                game::TileState::Uncovered     => false,
+            })
+            .collect()
+    }
+
+    // step the cursor, bringing it up on the tile nearest the middle of the board if it isn't
+    // showing yet
+    fn move_cursor(&mut self, (di, dj): (i32, i32)) {
+        let dims = self.state.dims();
+        let tiles = self.state.tiles();
+        let wrap = tiles.shape().wrap;
+        self.cursor = match self.cursor {
+            // over any gaps to the next tile, round the back of edges that wrap, staying put at
+            // those that don't
+            Some(ij) => Some((1 ..= dims.x + dims.y)
+                .map(|k| Coords::new(ij.x + k * di, ij.y + k * dj))
+                .take_while(|p| (wrap.x || (0 .. dims.x).contains(&p.x))
+                             && (wrap.y || (0 .. dims.y).contains(&p.y)))
+                .find_map(|p| tiles.wrapped(p))
+                .unwrap_or(ij)),
+            None => {
+                let middle = Coords::new(dims.x / 2, dims.y / 2);
+                tiles.indices().min_by_key(|&p| (p - middle).dot(&(p - middle)))
+            }
+        };
+        self.dirty = true;
+    }
+
+    fn restart(&mut self, Context { ctx, animator, settings, .. }: Context) {
+        self.abandon(ctx);
+        self.state.restart();
+        self.record(ctx, settings, Event::Restart(self.state.seed()));
+        animator.clear_animations();
+        self.dirty = true;
+    }
+
+    // keep a replay of the session, written out whenever a game finishes or is abandoned, and
+    // enter finished games in the high scores and statistics
+    fn record(&mut self, ctx: &ggez::Context, settings: &Settings, event: Event) {
+        let at = self.clock.now();
+        self.replay.record(at, event);
+
+        if is_click(event) {
+            self.clicks += 1;
+        }
+
+        if let Event::Restart(_) = event {
+            self.scored = false;
+            self.clicks = 0;
+            self.counted = false;
+            self.save_replay(ctx);
+        }
+        else if self.state.done() {
+            if self.state.status() == game::Status::Won && !self.scored {
+                highscores::add(ctx, settings, &self.state);
+                self.scored = true;
+            }
+
+            if !self.counted {
+                statistics::add(ctx, &self.state, self.clicks);
+                self.counted = true;
+            }
+
+            self.save_replay(ctx);
+        }
+    }
+
+    fn save_replay(&self, ctx: &ggez::Context) {
+        if self.replay.is_empty() { return; }
+
+        let dir = ggez::filesystem::user_data_dir(ctx).join("replays");
+        let result = std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::File::create(dir.join(&self.replay_name)))
+            .and_then(|file| {
+                let mut w = std::io::BufWriter::new(file);
+                self.replay.write(&mut w)?;
+                w.flush()
+            });
+
+        if let Err(err) = result {
+            eprintln!("couldn't save replay: {}", err);
+        }
     }
 }
 
 impl Activity for Play {
     fn mouse_down<'a> (
         &mut self,
-        Context { ctx, assets, animator }: Context<'a>,
+        Context { ctx, assets, animator, settings }: Context<'a>,
         button: MouseButton, position: P2)
     {
-        if position.y < BAR_HEIGHT {
-            let bar_rect = ggez::graphics::screen_coordinates(ctx);
-            if (position.x - bar_rect.w * 0.5).abs() < BAR_HEIGHT * 0.5 {
+        let already_held = self.held.clone();
+        if !self.held.contains(&button) {
+            self.held.push(button);
+        }
+
+        let layout = self.layout;
+        if position.y < layout.bar {
+            if position.x < layout.bar {
+                self.switch = Some(Switch::Configure(self.state.config().clone()));
+            }
+            else if (position.x - layout.width * 0.5).abs() < layout.bar * 0.5 {
                 self.boop = true;
-                self.state.restart();
-                animator.clear_animations();
+                self.restart(Context { ctx, assets, animator, settings });
             }
+
+            self.dirty = true;
+            return;
+        }
+
+        // a second button going down along with the first can make a chord of them
+        let bindings = &settings.bindings;
+        let with_held = already_held.iter().any(|&other| bindings.chord.has_buttons(other, button));
+        let pressing = if with_held || bindings.chord.has_button(button) {
+            Pressing::Chord
+        }
+        else if bindings.dig.has_button(button) {
+            Pressing::Dig
+        }
+        else if bindings.flag.has_button(button) {
+            Pressing::Flag
         }
         else {
-            let ij = (position.coords - V2::new(0., BAR_HEIGHT))
-                .map(|x| ((x / TILE_SIZE).trunc() as i32))
-                .zip_map(&self.state.dims(), i32::min)
-                .map(|x| x.max(0))
-                .into();
-
-            match button {
-                MouseButton::Left => {
-                    let dug = self.state.dig(ij);
-                    for (ij, boom) in dug.iter().copied() {
-                        let boom = match boom {
-                            Some(boom) => boom,
-                            None       => continue
-                        };
-
-                        let center = P2::new(0., BAR_HEIGHT)
-                                   + ij.coords.map(|x| (x as f32 + 0.5) * TILE_SIZE);
+            return;
+        };
 
-                        let key = SimpleKey::new()
-                            .position(center);
+        self.press = Some(Press { button, pressing, at: self.tile_at(position) });
+        self.dirty = true;
+    }
 
-                        if boom {
-                            animator.animate_simple(
-                                2.0,
-                                assets.circle.clone(),
-                                key.color(V4::new(1.0, 1.0, 0.0, 0.5)),
-                                key.color(V4::new(0.3, 0.0, 0.0, 0.0))
-                                    .scale(V2::repeat(TILE_SIZE * 30.)),
-                            );
-                        }
-                        else {
-                            animator.animate_simple(
-                                0.2,
-                                assets.circle.clone(),
-                                key.color(V4::new(1.0, 1.0, 1.0, 0.5)),
-                                key.color(V4::new(0.0, 0.5, 1.0, 0.0))
-                                    .scale(V2::repeat(TILE_SIZE * 1.2)),
-                            );
-                        }
-                    }
+    fn mouse_up<'a> (
+        &mut self,
+        Context { ctx, assets, animator, settings }: Context<'a>,
+        button: MouseButton, position: P2)
+    {
+        if self.boop {
+            self.boop = false;
+            self.dirty = true;
+        }
 
-                }
+        self.held.retain(|&held| held != button);
 
-                MouseButton::Right => {
-                    self.state.flag(ij);
-                }
+        // letting go of either button of a chord sets it off, and the other one then does nothing
+        let press = match self.press {
+            Some(press) if press.button == button || press.pressing == Pressing::Chord => press,
+            _                                                                         => return
+        };
+        self.press = None;
+        self.dirty = true;
+
+        // let go off the board: never mind
+        let ij = match self.tile_at(position) {
+            Some(ij) => ij,
+            None     => return
+        };
+
+        let action = match press.pressing {
+            Pressing::Dig   => game::Action::Dig(ij),
+            Pressing::Flag  => game::Action::Flag(ij),
+            // digging an uncovered number is what chords it
+            Pressing::Chord if self.state.tiles()[ij].state == game::TileState::Uncovered => {
+                game::Action::Dig(ij)
+            }
+            Pressing::Chord => return,
+        };
+
+        // a cursor in use follows the mouse's clicks
+        if self.cursor.is_some() {
+            self.cursor = Some(ij);
+        }
 
-                _ => { }
+        self.act(Context { ctx, assets, animator, settings }, action);
+    }
+
+    fn mouse_move<'a> (&mut self, _context: Context<'a>, position: P2) {
+        if let Some(press) = self.press {
+            let at = self.tile_at(position);
+            if at != press.at {
+                self.press = Some(Press { at, ..press });
+                self.dirty = true;
             }
+        }
+    }
+
+    fn resized<'a> (&mut self, _context: Context<'a>, width: f32, height: f32) {
+        self.size = Some((width, height));
+        self.dirty = true;
+    }
+
+    fn key_down<'a> (
+        &mut self,
+        Context { ctx, assets, animator, settings }: Context<'a>,
+        key: KeyCode, mods: KeyMods)
+    {
+        let bindings = &settings.bindings;
+        if bindings.hint.has_key(key, mods) {
+            if let Some(ij) = self.state.hint() {
+                let center = self.layout.tile_center(ij);
 
-            if self.state.status() == game::Status::Won {
-                let bar_rect = ggez::graphics::screen_coordinates(ctx);
-                let bar_center = P2::new(bar_rect.w * 0.5, BAR_HEIGHT * 0.5);
+                // green to dig, red to flag
+                let color = match self.state.tiles()[ij].kind {
+                    game::TileKind::Dirt    => V4::new(0.3, 1.0, 0.3, 0.8),
+                    game::TileKind::Mine(_) => V4::new(1.0, 0.3, 0.3, 0.8),
+                };
 
                 let key = SimpleKey::new()
-                    .position(bar_center);
+                    .position(center);
 
                 animator.animate_simple(
-                    1.5,
-                    assets.star.clone(),
-                    key.color(V4::new(1.0, 1.0, 1.0, 1.)),
-                    key.color(V4::new(1.0, 0.4, 0.7, 0.))
-                        .scale(V2::repeat(bar_rect.w * 1.5))
-                        .angle(10.),
+                    1.0,
+                    assets.circle.clone(),
+                    key.color(color)
+                        .scale(V2::repeat(self.layout.tile * 2.)),
+                    key.color(V4::new(color.x, color.y, color.z, 0.))
+                        .scale(V2::repeat(self.layout.tile * 0.3)),
                 );
             }
+            return;
         }
 
-        self.dirty = true;
-    }
+        let step = if bindings.up.has_key(key, mods) {
+            Some((0, -1))
+        }
+        else if bindings.down.has_key(key, mods) {
+            Some((0, 1))
+        }
+        else if bindings.left.has_key(key, mods) {
+            Some((-1, 0))
+        }
+        else if bindings.right.has_key(key, mods) {
+            Some((1, 0))
+        }
+        else {
+            None
+        };
 
-    fn mouse_up<'a> (&mut self, _context: Context<'a>, button: MouseButton, position: P2) {
-        if self.boop {
-            self.boop = false;
+        if let Some(step) = step {
+            self.move_cursor(step);
+            return;
+        }
+
+        if bindings.restart.has_key(key, mods) {
+            self.restart(Context { ctx, assets, animator, settings });
+            return;
+        }
+
+        if bindings.configure.has_key(key, mods) {
+            self.switch = Some(Switch::Configure(self.state.config().clone()));
+            return;
+        }
+
+        let at_cursor = bindings.dig.has_key(key, mods)
+                     || bindings.flag.has_key(key, mods)
+                     || bindings.chord.has_key(key, mods);
+        if at_cursor {
+            // the first press only brings the cursor up, so it's clear where the next one lands
+            let ij = match self.cursor {
+                Some(ij) => ij,
+                None     => return self.move_cursor((0, 0)),
+            };
+
+            let action = if bindings.flag.has_key(key, mods) {
+                game::Action::Flag(ij)
+            }
+            // chording is digging an uncovered number, so a chord on anything else does nothing
+            else if bindings.dig.has_key(key, mods)
+                || self.state.tiles()[ij].state == game::TileState::Uncovered
+            {
+                game::Action::Dig(ij)
+            }
+            else {
+                return;
+            };
+
+            self.act(Context { ctx, assets, animator, settings }, action);
+            return;
+        }
+
+        let action = if bindings.undo.has_key(key, mods) {
+            game::Action::Undo
+        }
+        else if bindings.redo.has_key(key, mods) {
+            game::Action::Redo
+        }
+        else {
+            return;
+        };
+
+        let changed = match action {
+            game::Action::Undo => self.state.undo(),
+            _                  => self.state.redo(),
+        };
+
+        if changed {
+            self.record(ctx, settings, Event::Action(action));
+            animator.clear_animations();
             self.dirty = true;
         }
     }
 
     fn draw<'a> (
         &mut self,
-        Context { ctx, assets, animator }: Context<'a>)
+        Context { ctx, assets, animator, settings }: Context<'a>)
         -> GameResult
     {
-        use ggez::graphics::{Align, clear, draw, DrawParam, screen_coordinates};
+        use ggez::graphics::{Align, clear, draw, DrawParam};
 
         let now = std::time::Instant::now();
 
-        clear(ctx, (0., 0., 0.).into());
+        let config = self.state.config();
+        let (width, height) = self.size
+            .unwrap_or_else(|| window_size(config, TILE_SIZE * settings.scale));
+        self.layout = fit_window(ctx, config, width, height)?;
+        assets.set_tile(ctx, self.layout.tile)?;
+        let layout = self.layout;
 
-        let bar_rect = {
-            let mut rect = screen_coordinates(ctx);
-            rect.h = BAR_HEIGHT;
-            rect.w -= 6.;
-            rect.x += 3.;
-            rect
-        };
+        let b = settings.theme.background();
+        clear(ctx, (b, b, b).into());
 
         let face = if self.boop {
             &assets.texts.boop
@@ -174,107 +702,261 @@ impl Activity for Play {
             }
         };
 
-        let bar_center = (bar_rect.w - BAR_HEIGHT) * 0.5;
+        let bar_center = (layout.width - 6. - layout.bar) * 0.5;
 
         let params = DrawParam::new()
             .dest(P2::new(bar_center, 0.));
         draw(ctx, face, params)?;
 
+        draw(ctx, &assets.texts.gear, DrawParam::new())?;
+        draw_timer(ctx, assets, &layout, &self.state)?;
+
         let mut count = assets.texts.stat.clone();
         count.fragments_mut()[1].text = format!("{:3}", self.state.flags_remaining());
-        count.set_bounds(P2::new(bar_rect.w - 6., bar_rect.h), Align::Right);
+        count.set_bounds(P2::new(layout.width - 12., layout.bar), Align::Right);
         draw(ctx, &count, DrawParam::new().dest(P2::new(3., 0.)))?;
 
-        for (coords, tile) in self.state.enumerate_tiles() {
-            let mut hasher = std::collections::hash_map::DefaultHasher::new();
-            use std::hash::Hasher;
-            hasher.write_i32(coords.x);
-            hasher.write_i32(coords.y);
-            let hash = hasher.finish();
-
-            let hazard = &assets.texts.hazards[hash as usize % assets.texts.hazards.len()];
-
-            let frac = hash as f64 / std::u64::MAX as f64;
-
-            let sh = 0.6 + 0.4 * frac as f32;
-            let c = sh * TONE_FOREGROUND;
-
-            let position = P2::new(0., BAR_HEIGHT)
-                         + V2::new(coords.x as f32, coords.y as f32) * TILE_SIZE;
-            let params = DrawParam::new()
-                .dest(position);
-
-            use game::{TileState, TileKind};
-            match tile.state {
-                TileState::Covered(flag) => {
-                    if self.state.done() {
-                        if flag {
-                            match tile.kind {
-                                TileKind::Mine => {
-                                    draw(ctx, &assets.square, params.color((0., c, 0.).into()))?;
-                                    draw(ctx, &assets.texts.tick, params)?;
-                                }
-
-                                TileKind::Dirt => {
-                                    draw(ctx, &assets.square, params.color((c, 0., 0.).into()))?;
-                                    draw(ctx, &assets.texts.nope, params)?;
-                                }
-                            }
+        draw_tiles(ctx, assets, &layout, settings.theme, &self.state)?;
+        draw_seams(ctx, assets, &layout, &self.state)?;
+        if !self.state.done() {
+            let c = settings.theme.background_tone();
+            for ij in self.pressed_tiles() {
+                draw(ctx, assets.cell(layout.tiling), DrawParam::new()
+                    .dest(layout.tile_position(ij))
+                    .color((c, c, c).into()))?;
+            }
+        }
+        if let Some(ij) = self.cursor {
+            draw(ctx, assets.cell_outline(layout.tiling), DrawParam::new()
+                .dest(layout.tile_position(ij))
+                .color((1., 0.8, 0.).into()))?;
+        }
+        if self.state.done() {
+            draw_summary(ctx, assets, &layout, &self.state)?;
+        }
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn close<'a> (&mut self, Context { ctx, .. }: Context<'a>) {
+        self.save_replay(ctx);
+
+        if !self.state.done() && !self.replay.is_empty() {
+            self.save_game(ctx);
+        }
+    }
+
+    fn focus<'a> (&mut self, _context: Context<'a>, focused: bool) {
+        // buttons let go of elsewhere never come back up here
+        self.held.clear();
+        self.press = None;
+
+        if focused {
+            self.clock.start();
+        }
+        else {
+            self.clock.stop();
+        }
+    }
+
+    fn dirty(&self) -> bool {
+        // keep drawing while the timer ticks, which it doesn't while the window is out of focus
+        self.dirty || (self.state.timer_running() && self.clock.is_running())
+    }
+
+    fn switch(&mut self) -> Option<Switch> {
+        self.switch.take()
+    }
+}
+
+// seconds on the timer, just right of the leftmost bar button
+pub fn draw_timer(ctx: &mut ggez::Context, assets: &Assets, layout: &Layout, state: &game::State)
+    -> GameResult
+{
+    use ggez::graphics::{draw, DrawParam};
+
+    let mut text = assets.texts.label.clone();
+    text.fragments_mut()[0].text = format!("{:03}", (state.elapsed() / 1000).min(999));
+    draw(ctx, &text, DrawParam::new().dest(P2::new(layout.bar + 3., layout.bar * 0.2)))
+}
+
+// 3BV across the middle of a finished board, and on a win how fast it was cleared
+fn draw_summary(ctx: &mut ggez::Context, assets: &Assets, layout: &Layout, state: &game::State)
+    -> GameResult
+{
+    use ggez::graphics::{Align, draw, DrawParam};
+
+    let bbbv = metrics::measure(state.tiles()).bbbv;
+    let mut summary = match state.status() {
+        game::Status::Won => format!("3BV {}   {:.2} 3BV/s", bbbv, metrics::rate(bbbv, state.elapsed())),
+        _                 => format!("3BV {}", bbbv),
+    };
+    // no-guess generation gave up on this one
+    if state.unproven() {
+        summary.push_str("   guess may be needed");
+    }
+
+    let width = layout.width;
+    let config = state.config();
+    let (_, h) = config.tiling.extent(config.width, config.height);
+    let top = layout.origin.y + (h * layout.tile - layout.bar) * 0.5;
+    draw(ctx, &assets.square, DrawParam::new()
+        .dest(P2::new(0., top))
+        .scale(V2::new(width / layout.tile, BAR_SCALE))
+        .color((0., 0., 0., 0.7).into()))?;
+
+    let mut text = assets.texts.label.clone();
+    text.fragments_mut()[0].text = summary;
+    text.set_bounds(P2::new(width, layout.bar), Align::Center);
+    draw(ctx, &text, DrawParam::new().dest(P2::new(0., top + layout.bar * 0.2)))
+}
+
+// matching strips along edges that wrap, to show that they join up
+pub fn draw_seams(ctx: &mut ggez::Context, assets: &Assets, layout: &Layout, state: &game::State)
+    -> GameResult
+{
+    use ggez::graphics::{draw, DrawParam};
+
+    let config = state.config();
+    let wrap = state.tiles().shape().wrap;
+    let (w, h) = config.tiling.extent(config.width, config.height);
+    let (w, h) = (w * layout.tile, h * layout.tile);
+    let thickness = (layout.tile * 0.1).max(2.);
+
+    let mut strips = Vec::new();
+    if wrap.x {
+        strips.push((P2::new(0., 0.),             V2::new(thickness, h)));
+        strips.push((P2::new(w - thickness, 0.), V2::new(thickness, h)));
+    }
+    if wrap.y {
+        strips.push((P2::new(0., 0.),             V2::new(w, thickness)));
+        strips.push((P2::new(0., h - thickness), V2::new(w, thickness)));
+    }
+
+    for (corner, size) in strips {
+        draw(ctx, &assets.square, DrawParam::new()
+            .dest(layout.origin + corner.coords)
+            .scale(size / layout.tile)
+            .color((0.3, 0.6, 1.0, 0.6).into()))?;
+    }
+
+    Ok(())
+}
+
+// a small count in the corner of a tile holding more than one mine or flag
+fn draw_count(ctx: &mut ggez::Context, assets: &Assets, layout: &Layout, ij: Coords, n: usize)
+    -> GameResult
+{
+    if n < 2 { return Ok(()); }
+
+    let params = ggez::graphics::DrawParam::new()
+        .dest(layout.tile_center(ij))
+        .scale(V2::repeat(0.5));
+    match assets.texts.digits.get(n - 1) {
+        Some(text) => ggez::graphics::draw(ctx, text, params),
+        None       => Ok(()),
+    }
+}
+
+pub fn draw_tiles(
+    ctx:    &mut ggez::Context,
+    assets: &Assets,
+    layout: &Layout,
+    theme:  Theme,
+    state:  &game::State)
+    -> GameResult
+{
+    use ggez::graphics::{draw, DrawParam};
+
+    let cell = assets.cell(layout.tiling);
+    for (coords, tile) in state.enumerate_tiles() {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        use std::hash::Hasher;
+        hasher.write_i32(coords.x);
+        hasher.write_i32(coords.y);
+        let hash = hasher.finish();
+
+        let hazard = &assets.texts.hazards[hash as usize % assets.texts.hazards.len()];
+
+        let frac = hash as f64 / std::u64::MAX as f64;
+
+        let sh = 0.6 + 0.4 * frac as f32;
+        let c = sh * theme.foreground_tone();
+
+        let params = DrawParam::new()
+            .dest(layout.tile_position(coords));
+
+        use game::{Mark, TileState, TileKind};
+        match tile.state {
+            TileState::Covered(mark) => {
+                let flag = tile.flags() > 0;
+                if state.done() {
+                    if flag {
+                        // right only if the flags match the mines underneath
+                        if tile.flags() == tile.mines() {
+                            draw(ctx, cell, params.color((0., c, 0.).into()))?;
+                            draw(ctx, &assets.texts.tick, params)?;
                         }
                         else {
-                            match tile.kind {
-                                TileKind::Mine => {
-                                    draw(ctx, &assets.square, params.color((c, 0., 0.).into()))?;
-                                    draw(ctx, hazard, params)?;
-                                }
-
-                                TileKind::Dirt => {
-                                    draw(ctx, &assets.square, params.color((c, c, c).into()))?;
-                                }
-                            }
+                            draw(ctx, cell, params.color((c, 0., 0.).into()))?;
+                            draw(ctx, &assets.texts.nope, params)?;
                         }
+                        draw_count(ctx, assets, layout, coords, tile.flags())?;
                     }
                     else {
-                        if flag {
-                            draw(ctx, &assets.square, params.color((0., c, c).into()))?;
-                            draw(ctx, &assets.texts.flag, params)?;
-                        }
-                        else {
-                            draw(ctx, &assets.square, params.color((c, c, c).into()))?;
+                        match tile.kind {
+                            TileKind::Mine(n) => {
+                                draw(ctx, cell, params.color((c, 0., 0.).into()))?;
+                                draw(ctx, hazard, params)?;
+                                draw_count(ctx, assets, layout, coords, n)?;
+                            }
+
+                            TileKind::Dirt => {
+                                draw(ctx, cell, params.color((c, c, c).into()))?;
+                            }
                         }
                     }
                 }
+                else {
+                    if flag {
+                        draw(ctx, cell, params.color((0., c, c).into()))?;
+                        draw(ctx, &assets.texts.flag, params)?;
+                        draw_count(ctx, assets, layout, coords, tile.flags())?;
+                    }
+                    else if mark == Mark::Question {
+                        draw(ctx, cell, params.color((c, c, c).into()))?;
+                        draw(ctx, &assets.texts.question, params)?;
+                    }
+                    else {
+                        draw(ctx, cell, params.color((c, c, c).into()))?;
+                    }
+                }
+            }
 
-                TileState::Uncovered => {
-                    match tile.kind {
-                        TileKind::Dirt => {
-                            let c = sh * TONE_BACKGROUND;
-                            draw(ctx, &assets.square, params.color((c, c, c).into()))?;
+            TileState::Uncovered => {
+                match tile.kind {
+                    TileKind::Dirt => {
+                        let c = sh * theme.background_tone();
+                        draw(ctx, cell, params.color((c, c, c).into()))?;
 
-                            if tile.n_near > 0 {
-                                let i = tile.n_near - 1;
-                                let text = &assets.texts.digits[i];
-                                draw(ctx, text, params)?;
-                            }
+                        let text = tile.n_near.checked_sub(1).and_then(|i| assets.texts.digits.get(i));
+                        if let Some(text) = text {
+                            draw(ctx, text, params)?;
                         }
+                    }
 
-                        TileKind::Mine => {
-                            draw(ctx, &assets.square, params.color((1., 1., 0.).into()))?;
-                            let mut hazard = hazard.clone();
-                            hazard.fragments_mut()[0].color = None;//Some(graphics::BLACK;
-                            draw(ctx, &hazard, params.color(ggez::graphics::BLACK))?;
-                        }
+                    TileKind::Mine(n) => {
+                        draw(ctx, cell, params.color((1., 1., 0.).into()))?;
+                        let mut hazard = hazard.clone();
+                        hazard.fragments_mut()[0].color = None;//Some(graphics::BLACK;
+                        draw(ctx, &hazard, params.color(ggez::graphics::BLACK))?;
+                        draw_count(ctx, assets, layout, coords, n)?;
                     }
                 }
             }
         }
-
-        self.dirty = false;
-        Ok(())
     }
 
-    fn dirty(&self) -> bool {
-        self.dirty
-    }
+    Ok(())
 }
-
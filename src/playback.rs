@@ -0,0 +1,259 @@
+
+use {
+    super::maths::*,
+    crate::{
+        activity::*,
+        clock::ManualClock,
+        game,
+        play::{self, Layout, TILE_SIZE},
+        replay::Replay,
+    },
+    std::rc::Rc,
+    ggez::{
+        GameResult,
+        input::{
+            keyboard::{KeyCode, KeyMods},
+            mouse::MouseButton,
+        },
+    },
+};
+
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 16.;
+
+// how far the arrow keys jump, in milliseconds
+const SEEK_STEP: u64 = 5000;
+
+const PROGRESS_HEIGHT: f32 = 4.;
+
+pub struct Playback {
+    replay: Replay,
+    state:  game::State,
+    // set to the replay's time, so the game's timer reads as it did when recorded
+    clock:  Rc<ManualClock>,
+
+    // the next event to apply, and how far in we are, in milliseconds
+    next:     usize,
+    position: f64,
+
+    speed:   f64,
+    playing: bool,
+    ticked:  std::time::Instant,
+
+    // the window size, once the player has chosen one
+    size:   Option<(f32, f32)>,
+    layout: Layout,
+
+    dirty: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        let clock = Rc::new(ManualClock::default());
+        let mut state = replay.fresh_state();
+        state.set_clock(clock.clone());
+
+        let (width, height) = play::window_size(replay.config(), TILE_SIZE);
+        let layout = Layout::fit(replay.config(), width, height);
+
+        Playback {
+            replay,
+            state,
+            clock,
+            next:     0,
+            position: 0.,
+            speed:    1.,
+            playing:  true,
+            ticked:   std::time::Instant::now(),
+            size:     None,
+            layout,
+            dirty:    true,
+        }
+    }
+
+    fn advance(&mut self, to: f64) {
+        let events = self.replay.events();
+        while let Some(&(at, event)) = events.get(self.next) {
+            if at as f64 > to { break; }
+            self.clock.set(at);
+            self.replay.apply(&mut self.state, event);
+            self.next += 1;
+        }
+
+        self.clock.set(to as u64);
+        self.position = to;
+    }
+
+    fn seek(&mut self, to: f64) {
+        let to = to.max(0.).min(self.replay.duration() as f64);
+        if to < self.position {
+            self.clock.set(0);
+            self.state = self.replay.fresh_state();
+            self.state.set_clock(self.clock.clone());
+            self.next = 0;
+        }
+
+        self.advance(to);
+        self.dirty = true;
+    }
+
+    fn toggle(&mut self) {
+        if !self.playing && self.position >= self.replay.duration() as f64 {
+            self.seek(0.);
+        }
+
+        self.playing = !self.playing;
+        self.ticked = std::time::Instant::now();
+        self.dirty = true;
+    }
+
+    fn tick(&mut self) {
+        let now = std::time::Instant::now();
+        let dt = (now - self.ticked).as_secs_f64() * 1000.;
+        self.ticked = now;
+
+        if !self.playing { return; }
+
+        let end = self.replay.duration() as f64;
+        let to = (self.position + dt * self.speed).min(end);
+        self.advance(to);
+
+        if to >= end {
+            self.playing = false;
+        }
+    }
+}
+
+impl Activity for Playback {
+    fn mouse_down<'a> (
+        &mut self,
+        Context { animator, .. }: Context<'a>,
+        button: MouseButton, position: P2)
+    {
+        let bar = self.layout.bar;
+        if button != MouseButton::Left || position.y >= bar {
+            return;
+        }
+
+        if position.x < bar {
+            self.toggle();
+        }
+        else {
+            let frac = (position.x - bar) / (self.layout.width - bar);
+            self.seek(frac as f64 * self.replay.duration() as f64);
+        }
+
+        animator.clear_animations();
+    }
+
+    fn mouse_up<'a> (&mut self, _context: Context<'a>, _button: MouseButton, _position: P2) {
+    }
+
+    fn mouse_move<'a> (&mut self, _context: Context<'a>, _position: P2) {
+    }
+
+    fn resized<'a> (&mut self, _context: Context<'a>, width: f32, height: f32) {
+        self.size = Some((width, height));
+        self.dirty = true;
+    }
+
+    fn key_down<'a> (
+        &mut self,
+        Context { animator, .. }: Context<'a>,
+        key: KeyCode, _mods: KeyMods)
+    {
+        match key {
+            KeyCode::Space => self.toggle(),
+            KeyCode::Left  => self.seek(self.position - SEEK_STEP as f64),
+            KeyCode::Right => self.seek(self.position + SEEK_STEP as f64),
+            KeyCode::Home  => self.seek(0.),
+            KeyCode::End   => self.seek(self.replay.duration() as f64),
+            KeyCode::Up    => self.speed = (self.speed * 2.).min(MAX_SPEED),
+            KeyCode::Down  => self.speed = (self.speed * 0.5).max(MIN_SPEED),
+            _              => return
+        }
+
+        animator.clear_animations();
+        self.dirty = true;
+    }
+
+    fn draw<'a> (
+        &mut self,
+        Context { ctx, assets, settings, .. }: Context<'a>)
+        -> GameResult
+    {
+        use ggez::graphics::{Align, clear, draw, DrawParam};
+
+        let config = self.replay.config();
+        let (width, height) = self.size
+            .unwrap_or_else(|| play::window_size(config, TILE_SIZE * settings.scale));
+        self.layout = play::fit_window(ctx, config, width, height)?;
+        assets.set_tile(ctx, self.layout.tile)?;
+        let layout = self.layout;
+
+        self.tick();
+
+        let b = settings.theme.background();
+        clear(ctx, (b, b, b).into());
+
+        let control = if self.playing { &assets.texts.pause } else { &assets.texts.play };
+        draw(ctx, control, DrawParam::new())?;
+
+        let face = {
+            use game::Status::*;
+            match self.state.status() {
+                Playing => &assets.texts.okay,
+                Won     => &assets.texts.woop,
+                Dead    => &assets.texts.ohno
+            }
+        };
+        draw(ctx, face, DrawParam::new().dest(P2::new((layout.width - layout.bar) * 0.5, 0.)))?;
+
+        let mut label = assets.texts.label.clone();
+        label.fragments_mut()[0].text = format!(
+            "×{}  {:.1}/{:.1}s",
+            self.speed,
+            self.position * 0.001,
+            self.replay.duration() as f64 * 0.001);
+        label.set_bounds(P2::new(layout.width - 6., layout.bar), Align::Right);
+        draw(ctx, &label, DrawParam::new().dest(P2::new(3., layout.bar * 0.2)))?;
+
+        // progress along the bottom of the bar
+        let track = layout.width - layout.bar;
+        let frac = match self.replay.duration() {
+            0        => 1.,
+            duration => (self.position / duration as f64) as f32,
+        };
+        let scale = |w: f32| V2::new(w / layout.tile, PROGRESS_HEIGHT / layout.tile);
+        let bottom = P2::new(layout.bar, layout.bar - PROGRESS_HEIGHT);
+        draw(ctx, &assets.square, DrawParam::new()
+            .dest(bottom)
+            .scale(scale(track))
+            .color((0.2, 0.2, 0.2).into()))?;
+        draw(ctx, &assets.square, DrawParam::new()
+            .dest(bottom)
+            .scale(scale(track * frac))
+            .color((0.3, 0.6, 1.0).into()))?;
+
+        play::draw_timer(ctx, assets, &layout, &self.state)?;
+        play::draw_tiles(ctx, assets, &layout, settings.theme, &self.state)?;
+        play::draw_seams(ctx, assets, &layout, &self.state)?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn close<'a> (&mut self, _context: Context<'a>) {
+    }
+
+    fn focus<'a> (&mut self, _context: Context<'a>, _focused: bool) {
+    }
+
+    fn dirty(&self) -> bool {
+        self.dirty || self.playing
+    }
+
+    fn switch(&mut self) -> Option<Switch> {
+        None
+    }
+}
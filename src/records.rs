@@ -0,0 +1,148 @@
+// what the high scores and statistics have in common: a file of records kept between runs, and
+// a window of rows to show them in.
+//
+// a file that's there but can't be read, whether damaged or from a newer version, is never saved
+// over. it's worth more than anything that could be put in its place.
+
+use {
+    crate::{
+        assets::Assets,
+        activity::resize_window,
+        format::Error,
+        maths::*,
+        play::{BAR_HEIGHT, TILE_SIZE},
+        scores::Scores,
+        settings::Settings,
+        stats::Stats,
+    },
+    std::{
+        io::{BufRead, Write},
+        path::Path,
+    },
+    ggez::{
+        GameResult,
+        graphics::{Align, Text},
+    },
+};
+
+pub const ROW_HEIGHT: f32 = BAR_HEIGHT * 0.8;
+
+pub trait Records: Default {
+    // what they're called in messages
+    const WHAT: &'static str;
+
+    fn read(r: impl BufRead) -> Result<Self, Error>;
+    fn write(&self, w: &mut impl Write) -> std::io::Result<()>;
+}
+
+impl Records for Scores {
+    const WHAT: &'static str = "high scores";
+
+    fn read(r: impl BufRead) -> Result<Scores, Error> {
+        Scores::read(r)
+    }
+
+    fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        Scores::write(self, w)
+    }
+}
+
+impl Records for Stats {
+    const WHAT: &'static str = "statistics";
+
+    fn read(r: impl BufRead) -> Result<Stats, Error> {
+        Stats::read(r)
+    }
+
+    fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        Stats::write(self, w)
+    }
+}
+
+// `None` if there's a file but it can't be read
+pub fn try_load<T: Records>(path: &Path) -> Option<T> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Some(T::default()),
+        Err(err) => {
+            eprintln!("couldn't open {} at {}: {}", T::WHAT, path.display(), err);
+            return None;
+        }
+    };
+
+    match T::read(std::io::BufReader::new(file)) {
+        Ok(records) => Some(records),
+        Err(err) => {
+            eprintln!("couldn't read {} from {}: {}", T::WHAT, path.display(), err);
+            None
+        }
+    }
+}
+
+fn save<T: Records>(path: &Path, records: &T) {
+    let result = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::File::create(path))
+        .and_then(|file| {
+            let mut w = std::io::BufWriter::new(file);
+            records.write(&mut w)?;
+            w.flush()
+        });
+
+    if let Err(err) = result {
+        eprintln!("couldn't save {} to {}: {}", T::WHAT, path.display(), err);
+    }
+}
+
+// read the file afresh, change it and save it back. `None`, with nothing saved, if it couldn't
+// be read.
+pub fn update<T: Records, R>(path: &Path, change: impl FnOnce(&mut T) -> R) -> Option<R> {
+    let mut records = match try_load::<T>(path) {
+        Some(records) => records,
+        None          => {
+            eprintln!("leaving {} alone", path.display());
+            return None;
+        }
+    };
+
+    let result = change(&mut records);
+    save(path, &records);
+    Some(result)
+}
+
+// where the text of row `i` goes
+pub fn row_dest(i: usize) -> P2 {
+    P2::new(12., i as f32 * ROW_HEIGHT + (ROW_HEIGHT - BAR_HEIGHT * 0.6) * 0.5)
+}
+
+// a line of text across a window `width` wide
+pub fn label(assets: &Assets, width: f32, text: String, align: Align) -> Text {
+    let mut label = assets.texts.label.clone();
+    label.fragments_mut()[0].text = text;
+    label.set_bounds(P2::new(width - 24., ROW_HEIGHT), align);
+    label
+}
+
+// size and clear a window of `n_rows` rows, and draw the title across the top one
+pub fn draw_frame(
+    ctx:      &mut ggez::Context,
+    assets:   &mut Assets,
+    settings: &Settings,
+    width:    f32,
+    n_rows:   usize,
+    title:    String)
+    -> GameResult
+{
+    use ggez::graphics::{clear, draw, DrawParam};
+
+    // laid out for tiles of the usual size, and scaled as a whole
+    assets.set_tile(ctx, TILE_SIZE)?;
+    resize_window(ctx, settings.scale, width, n_rows as f32 * ROW_HEIGHT)?;
+
+    let b = settings.theme.background();
+    clear(ctx, (b, b, b).into());
+
+    draw(ctx, &assets.square, DrawParam::new()
+        .scale(V2::new(width / TILE_SIZE, ROW_HEIGHT / TILE_SIZE))
+        .color((0.2, 0.2, 0.2).into()))?;
+    draw(ctx, &label(assets, width, title, Align::Center), DrawParam::new().dest(row_dest(0)))
+}
@@ -0,0 +1,136 @@
+
+use {
+    crate::{
+        format::{self, Error, Reader},
+        game::{Action, Config, State},
+        grid::Coords,
+    },
+    std::io::{BufRead, Write},
+};
+
+const KIND:    &str = "mines-replay";
+const VERSION: u32  = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Action(Action),
+    // a new game, laid out from the given seed
+    Restart(u64),
+}
+
+pub struct Replay {
+    // the seed is always filled in
+    config: Config,
+    // with milliseconds since recording began, in order
+    events: Vec<(u64, Event)>,
+}
+
+impl Replay {
+    pub fn new(state: &State) -> Replay {
+        let config = Config { seed: Some(state.seed()), ..state.config().clone() };
+        Replay { config, events: Vec::new() }
+    }
+
+    pub fn record(&mut self, at: u64, event: Event) {
+        let at = self.events.last().map_or(at, |&(last, _)| at.max(last));
+        self.events.push((at, event));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn events(&self) -> &[(u64, Event)] {
+        &self.events
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.events.last().map_or(0, |&(at, _)| at)
+    }
+
+    pub fn fresh_state(&self) -> State {
+        State::new(self.config.clone())
+    }
+
+    pub fn apply(&self, state: &mut State, event: Event) {
+        match event {
+            Event::Action(action) => state.apply(action),
+            Event::Restart(seed)  => state.restart_with_seed(Some(seed)),
+        }
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        writeln!(w, "{} {}", KIND, VERSION)?;
+        format::write_config(w, &self.config)?;
+        writeln!(w)?;
+        self.write_events(w)
+    }
+
+    pub fn write_events(&self, w: &mut impl Write) -> std::io::Result<()> {
+        for (at, event) in &self.events {
+            match event {
+                Event::Action(Action::Dig(ij))  => writeln!(w, "{} dig {} {}",  at, ij.x, ij.y)?,
+                Event::Action(Action::Flag(ij)) => writeln!(w, "{} flag {} {}", at, ij.x, ij.y)?,
+                Event::Action(Action::Undo)     => writeln!(w, "{} undo",       at)?,
+                Event::Action(Action::Redo)     => writeln!(w, "{} redo",       at)?,
+                Event::Restart(seed)            => writeln!(w, "{} restart {}", at, seed)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read(r: impl BufRead) -> Result<Replay, Error> {
+        let mut reader = Reader::new(r);
+        reader.header(KIND, VERSION)?;
+
+        let fields = reader.fields()?;
+        let config = format::read_config(&fields)?;
+        Self::read_events(config, &mut reader)
+    }
+
+    // reads the rest of `reader` as events
+    pub fn read_events<R> (config: Config, reader: &mut Reader<R>) -> Result<Replay, Error>
+        where R: BufRead
+    {
+        if config.seed.is_none() {
+            return Err(Error::Syntax { line: 0, message: "replay has no seed".to_owned() });
+        }
+
+        let (width, height) = (config.width, config.height);
+        let mut replay = Replay { config, events: Vec::new() };
+        while let Some((line, text)) = reader.body_line()? {
+            let mut words = text.split_whitespace();
+            let at: u64 = format::parse(line, words.next(), "time")?;
+
+            let coords = |words: &mut std::str::SplitWhitespace| -> Result<Coords, Error> {
+                let x: i32 = format::parse(line, words.next(), "x")?;
+                let y: i32 = format::parse(line, words.next(), "y")?;
+                if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                    return Err(Error::Syntax { line, message: format!("{},{} is off the board", x, y) });
+                }
+                Ok(Coords::new(x, y))
+            };
+
+            let event = match words.next() {
+                Some("dig")     => Event::Action(Action::Dig(coords(&mut words)?)),
+                Some("flag")    => Event::Action(Action::Flag(coords(&mut words)?)),
+                Some("undo")    => Event::Action(Action::Undo),
+                Some("redo")    => Event::Action(Action::Redo),
+                Some("restart") => Event::Restart(format::parse(line, words.next(), "seed")?),
+                other => {
+                    let message = format!("unknown event '{}'", other.unwrap_or_default());
+                    return Err(Error::Syntax { line, message });
+                }
+            };
+
+            replay.record(at, event);
+        }
+
+        Ok(replay)
+    }
+}
@@ -0,0 +1,340 @@
+
+// an unfinished game, kept between runs along with the replay recorded so far and the name of
+// the file it goes in
+
+use {
+    crate::{
+        format::{self, Error, Reader},
+        game::{Config, FlagLimit, Mark, State, Status, Tile, TileKind, TileState},
+        grid::{Coords, Grid},
+        replay::Replay,
+    },
+    std::io::{BufRead, Write},
+};
+
+const KIND:    &str = "mines-save";
+const VERSION: u32  = 1;
+
+// how many mines and flags a tile has are left out here. on boards that can stack them, they
+// follow the board in blocks of digits of their own.
+fn tile_char(tile: &Tile) -> char {
+    match (tile.kind, tile.state) {
+        (TileKind::Dirt,    TileState::Covered(Mark::Blank))    => '.',
+        (TileKind::Dirt,    TileState::Covered(Mark::Flag(_)))  => 'f',
+        (TileKind::Dirt,    TileState::Covered(Mark::Question)) => 'q',
+        (TileKind::Dirt,    TileState::Uncovered)               => '_',
+        (TileKind::Mine(_), TileState::Covered(Mark::Blank))    => '*',
+        (TileKind::Mine(_), TileState::Covered(Mark::Flag(_)))  => 'F',
+        (TileKind::Mine(_), TileState::Covered(Mark::Question)) => 'Q',
+        (TileKind::Mine(_), TileState::Uncovered)               => 'X',
+    }
+}
+
+fn char_tile(ch: char) -> Option<Tile> {
+    let (kind, state) = match ch {
+        '.' => (TileKind::Dirt,    TileState::Covered(Mark::Blank)),
+        'f' => (TileKind::Dirt,    TileState::Covered(Mark::Flag(1))),
+        'q' => (TileKind::Dirt,    TileState::Covered(Mark::Question)),
+        '_' => (TileKind::Dirt,    TileState::Uncovered),
+        '*' => (TileKind::Mine(1), TileState::Covered(Mark::Blank)),
+        'F' => (TileKind::Mine(1), TileState::Covered(Mark::Flag(1))),
+        'Q' => (TileKind::Mine(1), TileState::Covered(Mark::Question)),
+        'X' => (TileKind::Mine(1), TileState::Uncovered),
+        _   => return None
+    };
+
+    Some(Tile { kind, state, n_near: 0 })
+}
+
+fn write_rows(w: &mut impl Write, tiles: &Grid<Tile>, func: impl Fn(&Tile) -> char)
+    -> std::io::Result<()>
+{
+    for y in 0 .. tiles.height() as i32 {
+        let row: String = (0 .. tiles.width() as i32)
+            .map(|x| func(&tiles[Coords::new(x, y)]))
+            .collect();
+        writeln!(w, "{}", row)?;
+    }
+    Ok(())
+}
+
+fn read_rows<R, T> (reader: &mut Reader<R>, config: &Config, func: impl Fn(char) -> Option<T>)
+    -> Result<Vec<Vec<T>>, Error>
+    where R: BufRead
+{
+    let mut rows = Vec::new();
+    for _ in 0 .. config.height {
+        let (line, text) = reader.body_line()?
+            .ok_or_else(|| Error::Syntax { line: 0, message: "board is cut short".to_owned() })?;
+
+        let row: Option<Vec<T>> = text.trim().chars().map(&func).collect();
+        match row {
+            Some(row) if row.len() == config.width => rows.push(row),
+            _ => return Err(Error::Syntax { line, message: "bad board row".to_owned() }),
+        }
+    }
+
+    Ok(rows)
+}
+
+fn digit(n: usize) -> char {
+    std::char::from_digit(n as u32, 10).unwrap_or('?')
+}
+
+pub fn write(w: &mut impl Write, state: &State, replay: &Replay, replay_name: &str)
+    -> std::io::Result<()>
+{
+    writeln!(w, "{} {}", KIND, VERSION)?;
+    format::write_config(w, &Config { seed: Some(state.seed()), ..state.config().clone() })?;
+    writeln!(w, "placed {}",      state.mines_placed())?;
+    writeln!(w, "status {}",      state.status())?;
+    writeln!(w, "undo-used {}",   state.undo_used())?;
+    writeln!(w, "hint-used {}",   state.hint_used())?;
+    writeln!(w, "unproven {}",    state.unproven())?;
+    writeln!(w, "replay-seed {}", replay.config().seed.unwrap_or_default())?;
+    writeln!(w, "replay-name {}", replay_name)?;
+    writeln!(w, "timer-started {}", state.timer_started())?;
+    writeln!(w, "elapsed {}",       state.elapsed())?;
+    writeln!(w)?;
+
+    let tiles = state.tiles();
+    write_rows(w, tiles, tile_char)?;
+    if state.config().mines_per_tile > 1 {
+        writeln!(w)?;
+        write_rows(w, tiles, |tile| digit(tile.mines()))?;
+        writeln!(w)?;
+        write_rows(w, tiles, |tile| digit(tile.flags()))?;
+    }
+    writeln!(w)?;
+
+    replay.write_events(w)
+}
+
+pub fn read(r: impl BufRead) -> Result<(State, Replay, String), Error> {
+    let mut reader = Reader::new(r);
+    reader.header(KIND, VERSION)?;
+
+    let fields = reader.fields()?;
+    let config = format::read_config(&fields)?;
+    if config.seed.is_none() {
+        return Err(Error::Syntax { line: 0, message: "save has no seed".to_owned() });
+    }
+
+    let placed:    bool   = fields.get("placed")?;
+    let status:    Status = fields.get("status")?;
+    let undo_used: bool   = fields.get("undo-used")?;
+    let hint_used: bool   = fields.get("hint-used")?;
+    let unproven:  bool   = fields.get("unproven")?;
+    let replay_seed: u64  = fields.get("replay-seed")?;
+    let replay_name: String = fields.get("replay-name")?;
+    let timer_started: bool = fields.get("timer-started")?;
+    let elapsed:       u64  = fields.get("elapsed")?;
+
+    // it's only ever a file name, with the replays directory to go in
+    if std::path::Path::new(&replay_name).file_name() != Some(replay_name.as_ref()) {
+        return Err(Error::Syntax { line: 0, message: "bad replay name".to_owned() });
+    }
+
+    let mut rows = read_rows(&mut reader, &config, char_tile)?;
+    if config.mines_per_tile > 1 {
+        let count = |ch: char| ch.to_digit(10).map(|n| n as usize);
+        let mines = read_rows(&mut reader, &config, count)?;
+        let flags = read_rows(&mut reader, &config, count)?;
+        for (y, row) in rows.iter_mut().enumerate() {
+            for (x, tile) in row.iter_mut().enumerate() {
+                let (n_mines, n_flags) = (mines[y][x], flags[y][x]);
+                // the counts have to agree with the tile, and with the board
+                let fits = match &mut tile.kind {
+                    TileKind::Mine(n) if n_mines > 0 => { *n = n_mines; true }
+                    TileKind::Mine(_)                => false,
+                    TileKind::Dirt                   => n_mines == 0,
+                } && match &mut tile.state {
+                    TileState::Covered(Mark::Flag(n)) if n_flags > 0 => { *n = n_flags; true }
+                    TileState::Covered(Mark::Flag(_)) => false,
+                    _                                 => n_flags == 0,
+                };
+
+                if !fits || n_mines > config.mines_per_tile || n_flags > config.mines_per_tile {
+                    return Err(Error::Syntax { line: 0, message: "bad tile counts".to_owned() });
+                }
+            }
+        }
+    }
+
+    let tiles = Grid::new_generate(config.width, config.height, |ij| {
+        rows[ij.y as usize][ij.x as usize]
+    }).shaped(config.shape()).masked(config.mask());
+
+    let n_mines: usize = tiles.iter().map(Tile::mines).sum();
+    let n_flags: usize = tiles.iter().map(Tile::flags).sum();
+    let consistent = if placed {
+        n_mines == config.n_mines
+            && (n_flags <= config.n_mines || config.flags == FlagLimit::Unlimited)
+    }
+    else {
+        n_mines == 0 && tiles.iter().all(|tile| tile.state == TileState::Covered(Mark::Blank))
+    };
+
+    if !consistent {
+        return Err(Error::Syntax { line: 0, message: "board doesn't match its config".to_owned() });
+    }
+
+    let elapsed = if timer_started { Some(elapsed) } else { None };
+    let replay_config = Config { seed: Some(replay_seed), ..config.clone() };
+    let mut state = State::from_parts(config, tiles, placed, status, undo_used, hint_used, elapsed);
+    state.set_unproven(unproven);
+    let replay = Replay::read_events(replay_config, &mut reader)?;
+    Ok((state, replay, replay_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            clock::ManualClock,
+            game::{tests::config, Action, Outline, Topology},
+            replay::Event,
+        },
+        std::rc::Rc,
+    };
+
+    struct Game {
+        state:  State,
+        replay: Replay,
+        clock:  Rc<ManualClock>,
+    }
+
+    impl Game {
+        fn new(config: Config) -> Game {
+            let mut state = State::new(config);
+            let clock = Rc::new(ManualClock::default());
+            state.set_clock(clock.clone());
+            let replay = Replay::new(&state);
+            Game { state, replay, clock }
+        }
+
+        fn act(&mut self, at: u64, action: Action) {
+            self.clock.set(at);
+            self.state.apply(action);
+            self.replay.record(at, Event::Action(action));
+        }
+
+        // somewhere still covered and unmarked, with or without a mine
+        fn covered(&self, mine: bool) -> Coords {
+            self.state.enumerate_tiles()
+                .find(|(_, tile)| tile.state == TileState::Covered(Mark::Blank)
+                    && (tile.mines() > 0) == mine)
+                .map(|(ij, _)| ij)
+                .unwrap()
+        }
+
+        fn save(&self) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            write(&mut bytes, &self.state, &self.replay, "123.replay").unwrap();
+            bytes
+        }
+    }
+
+    // everything the save keeps, short of the time on a running timer
+    fn assert_same(loaded: &State, state: &State) {
+        assert_eq!(loaded.config(),          state.config());
+        assert_eq!(loaded.seed(),            state.seed());
+        assert_eq!(loaded.mines_placed(),    state.mines_placed());
+        assert_eq!(loaded.tiles(),           state.tiles());
+        assert_eq!(loaded.status(),          state.status());
+        assert_eq!(loaded.undo_used(),       state.undo_used());
+        assert_eq!(loaded.hint_used(),       state.hint_used());
+        assert_eq!(loaded.unproven(),        state.unproven());
+        assert_eq!(loaded.flags_remaining(), state.flags_remaining());
+        assert_eq!(loaded.timer_started(),   state.timer_started());
+        assert_eq!(loaded.timer_running(),   state.timer_running());
+    }
+
+    fn round_trip(game: &Game) -> State {
+        let (loaded, replay, replay_name) = read(&game.save()[..]).unwrap();
+        assert_same(&loaded, &game.state);
+        assert_eq!(replay_name, "123.replay");
+        assert_eq!(replay.config(), game.replay.config());
+        assert_eq!(replay.events(), game.replay.events());
+        loaded
+    }
+
+    #[test]
+    fn round_trip_in_play() {
+        let mut game = Game::new(Config { question_marks: true, ..config(9, 9, 10, 3) });
+        // before the mines are down
+        round_trip(&game);
+
+        game.act(1000, Action::Dig(Coords::new(4, 4)));
+        let (flagged, questioned) = (game.covered(true), game.covered(false));
+        game.act(1500, Action::Flag(flagged));
+        game.act(2000, Action::Flag(questioned));
+        game.act(2500, Action::Flag(questioned));
+        game.act(3000, Action::Undo);
+        game.state.hint();
+        game.clock.set(4000);
+
+        // still ticking, from where it was
+        let loaded = round_trip(&game);
+        assert!(loaded.elapsed() >= 3000 && loaded.elapsed() < 4000);
+
+        // stopped for good
+        game.act(5000, Action::Dig(game.covered(true)));
+        assert_eq!(game.state.status(), Status::Dead);
+        let loaded = round_trip(&game);
+        assert_eq!(loaded.elapsed(), 4000);
+    }
+
+    #[test]
+    fn round_trip_stacked() {
+        let mut game = Game::new(Config {
+            mines_per_tile: 3,
+            topology:       Topology::Torus,
+            outline:        Outline::Ring,
+            ..config(12, 12, 60, 5)
+        });
+        game.act(0, Action::Dig(Coords::new(6, 1)));
+        let stacked = game.covered(true);
+        for at in 1 ..= 2 {
+            game.act(at * 100, Action::Flag(stacked));
+        }
+        game.act(300, Action::Flag(game.covered(false)));
+
+        round_trip(&game);
+    }
+
+    #[test]
+    fn damaged_saves() {
+        let mut game = Game::new(config(9, 9, 10, 3));
+        game.act(1000, Action::Dig(Coords::new(4, 4)));
+        let text = String::from_utf8(game.save()).unwrap();
+        assert!(read(text.as_bytes()).is_ok());
+
+        // cut off part way through the board
+        let lines: Vec<&str> = text.lines().collect();
+        let blank = lines.iter().position(|line| line.is_empty()).unwrap();
+        let cut = lines[.. blank + 5].join("\n");
+        assert!(read(cut.as_bytes()).is_err());
+
+        let damaged = [
+            // a field gone
+            text.replace("elapsed", "ellapsed"),
+            // somewhere other than the replays directory
+            text.replace("replay-name 123.replay", "replay-name ../123.replay"),
+            text.replace("replay-name 123.replay", "replay-name"),
+            // a tile that isn't one
+            text.replacen("\n.", "\nz", 1),
+            // mines that don't add up
+            text.replace("mines 10", "mines 11"),
+            // a row too long
+            text.replacen("\n.", "\n..", 1),
+            // an event with nowhere to dig
+            text.clone() + "1000 dig\n",
+        ];
+        for damaged in &damaged {
+            assert_ne!(damaged, &text);
+            assert!(read(damaged.as_bytes()).is_err(), "{}", damaged);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+
+// the best times for each kind of board. games won with undo or hints are ranked in a table of
+// their own, so they never push out a clean win.
+
+use {
+    crate::{
+        format::{self, Error, Reader},
+        game::{Chording, Config, FlagLimit, Generation, Opening, Outline, State, Topology, WinRule},
+        grid::{Neighbourhood, Tiling},
+        metrics,
+    },
+    std::{
+        fmt,
+        io::{BufRead, Write},
+    },
+};
+
+const KIND:    &str = "mines-scores";
+const VERSION: u32  = 1;
+
+// how many entries each table keeps
+pub const TABLE_SIZE: usize = 10;
+
+// what makes two games comparable: the board and the rules, but not the seed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    pub width:      usize,
+    pub height:     usize,
+    pub n_mines:    usize,
+    pub generation: Generation,
+    pub opening:    Opening,
+    pub chording:   Chording,
+    pub win:        WinRule,
+    pub flags:      FlagLimit,
+    pub topology:   Topology,
+    pub tiling:     Tiling,
+    pub neighbourhood: Neighbourhood,
+    pub outline:    Outline,
+    pub mines_per_tile: usize,
+}
+
+impl Board {
+    pub fn new(config: &Config) -> Board {
+        Board {
+            width:      config.width,
+            height:     config.height,
+            n_mines:    config.n_mines,
+            generation: config.generation,
+            opening:    config.opening,
+            chording:   config.chording,
+            win:        config.win,
+            flags:      config.flags,
+            topology:   config.topology,
+            tiling:     config.tiling,
+            neighbourhood: config.neighbourhood,
+            outline:    config.outline.clone(),
+            mines_per_tile: config.mines_per_tile,
+        }
+    }
+
+    // where a game is filed. a no-guess game that settled for an unproven layout goes in with
+    // the random ones.
+    pub fn played(state: &State) -> Board {
+        let board = Board::new(state.config());
+        if state.unproven() {
+            Board { generation: Generation::Random, ..board }
+        }
+        else {
+            board
+        }
+    }
+
+    // the words after `board` on a line of its own
+    pub fn read<'a> (line: usize, words: &mut impl Iterator<Item = &'a str>) -> Result<Board, Error> {
+        Ok(Board {
+            width:      format::parse(line, words.next(), "width")?,
+            height:     format::parse(line, words.next(), "height")?,
+            n_mines:    format::parse(line, words.next(), "mines")?,
+            generation: format::parse(line, words.next(), "generation")?,
+            opening:    format::parse(line, words.next(), "opening")?,
+            chording:   format::parse(line, words.next(), "chording")?,
+            win:        format::parse(line, words.next(), "win")?,
+            flags:      format::parse(line, words.next(), "flags")?,
+            topology:   format::parse(line, words.next(), "topology")?,
+            tiling:     format::parse(line, words.next(), "tiling")?,
+            neighbourhood: format::parse(line, words.next(), "neighbourhood")?,
+            outline:    format::parse(line, words.next(), "outline")?,
+            mines_per_tile: format::parse(line, words.next(), "mines per tile")?,
+        })
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {} {} {} {} {} {} {} {} {} {}",
+            self.width, self.height, self.n_mines, self.generation, self.opening, self.chording,
+            self.win, self.flags, self.topology, self.tiling, self.neighbourhood, self.outline,
+            self.mines_per_tile)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    // milliseconds on the timer
+    pub time:     u64,
+    // seconds since the unix epoch
+    pub date:     u64,
+    pub seed:     u64,
+    // of the board, so that times on easy and hard layouts can be compared
+    pub bbbv:     usize,
+    pub assisted: bool,
+    pub name:     String,
+}
+
+impl Entry {
+    // an entry for a won game
+    pub fn new(state: &State, date: u64, name: &str) -> Entry {
+        Entry {
+            time:     state.elapsed(),
+            date,
+            seed:     state.seed(),
+            bbbv:     metrics::measure(state.tiles()).bbbv,
+            assisted: state.assisted(),
+            name:     name.to_owned(),
+        }
+    }
+
+    // 3BV per second
+    pub fn rate(&self) -> f64 {
+        metrics::rate(self.bbbv, self.time)
+    }
+}
+
+#[derive(Default)]
+pub struct Scores {
+    // best first, kept apart by board and by whether they were assisted
+    tables: Vec<(Board, bool, Vec<Entry>)>,
+}
+
+impl Scores {
+    pub fn table(&self, board: &Board, assisted: bool) -> &[Entry] {
+        self.tables.iter()
+            .find(|(b, a, _)| b == board && *a == assisted)
+            .map_or(&[], |(_, _, entries)| entries)
+    }
+
+    fn table_mut(&mut self, board: Board, assisted: bool) -> &mut Vec<Entry> {
+        let i = match self.tables.iter().position(|(b, a, _)| *b == board && *a == assisted) {
+            Some(i) => i,
+            None => {
+                self.tables.push((board, assisted, Vec::new()));
+                self.tables.len() - 1
+            }
+        };
+
+        &mut self.tables[i].2
+    }
+
+    // returns the entry's rank in its table, or None if it didn't make it in
+    pub fn add(&mut self, board: Board, entry: Entry) -> Option<usize> {
+        let entries = self.table_mut(board, entry.assisted);
+
+        // after any equal times, so the earlier win keeps its place
+        let rank = entries.iter().position(|e| e.time > entry.time).unwrap_or(entries.len());
+        entries.insert(rank, entry);
+        entries.truncate(TABLE_SIZE);
+
+        if rank < TABLE_SIZE { Some(rank) } else { None }
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        writeln!(w, "{} {}", KIND, VERSION)?;
+        writeln!(w)?;
+
+        for (board, _, entries) in &self.tables {
+            writeln!(w, "board {}", board)?;
+
+            for entry in entries {
+                let table = if entry.assisted { "assisted" } else { "clean" };
+                writeln!(w, "win {} {} {} {} {} {}",
+                    entry.time, entry.date, entry.seed, entry.bbbv, table, entry.name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read(r: impl BufRead) -> Result<Scores, Error> {
+        let mut reader = Reader::new(r);
+        reader.header(KIND, VERSION)?;
+        reader.fields()?;
+
+        let mut scores = Scores::default();
+        let mut board = None;
+        while let Some((line, text)) = reader.body_line()? {
+            let mut words = text.split_whitespace();
+            match words.next() {
+                Some("board") => board = Some(Board::read(line, &mut words)?),
+
+                Some("win") => {
+                    let time = format::parse(line, words.next(), "time")?;
+                    let date = format::parse(line, words.next(), "date")?;
+                    let seed = format::parse(line, words.next(), "seed")?;
+                    let bbbv = format::parse(line, words.next(), "3bv")?;
+                    let assisted = match words.next() {
+                        Some("clean")    => false,
+                        Some("assisted") => true,
+                        other => {
+                            let message = format!("bad table '{}'", other.unwrap_or_default());
+                            return Err(Error::Syntax { line, message });
+                        }
+                    };
+                    let name = words.collect::<Vec<_>>().join(" ");
+
+                    let board = board.clone().ok_or_else(|| {
+                        Error::Syntax { line, message: "win before any board".to_owned() }
+                    })?;
+                    scores.table_mut(board, assisted).push(Entry { time, date, seed, bbbv, assisted, name });
+                }
+
+                other => {
+                    let message = format!("unknown line '{}'", other.unwrap_or_default());
+                    return Err(Error::Syntax { line, message });
+                }
+            }
+        }
+
+        Ok(scores)
+    }
+}
+
+// a unix time as a year-month-day date, in UTC
+pub fn format_date(secs: u64) -> String {
+    // from Howard Hinnant's days-to-civil algorithm
+    let z = (secs / 86400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,275 @@
+
+// options that last between runs, kept in a file the player can edit by hand. anything unreadable
+// in it is warned about and left at its default, rather than stopping the game from starting.
+
+use {
+    crate::{
+        bindings::Bindings,
+        format::{self, Fields, Reader},
+        game,
+        grid,
+    },
+    std::{
+        fmt,
+        io::Write,
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+};
+
+const KIND:    &str = "mines-settings";
+const VERSION: u32  = 1;
+
+const FILE: &str = "settings";
+
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 4.;
+
+const KEYS: &[&str] = &[
+    "width", "height", "mines", "seed", "generation", "opening", "chording", "win", "flags",
+    "question-marks", "topology", "tiling", "neighbourhood", "outline", "mines-per-tile",
+    "name", "scale", "theme",
+    "bind-dig", "bind-flag", "bind-chord", "bind-undo", "bind-redo", "bind-hint",
+    "bind-up", "bind-down", "bind-left", "bind-right", "bind-restart", "bind-configure",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn background(self) -> f32 {
+        match self {
+            Theme::Dark  => 0.0,
+            Theme::Light => 0.6,
+        }
+    }
+
+    // brightness of covered tiles
+    pub fn foreground_tone(self) -> f32 {
+        match self {
+            Theme::Dark  => 0.50,
+            Theme::Light => 0.85,
+        }
+    }
+
+    // brightness of uncovered tiles
+    pub fn background_tone(self) -> f32 {
+        match self {
+            Theme::Dark  => 0.07,
+            Theme::Light => 0.30,
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Theme::Dark  => "dark",
+            Theme::Light => "light",
+        })
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Theme, String> {
+        match s {
+            "dark"  => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            _       => Err(format!("unknown theme '{}'", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    // what a new game is set up with
+    pub config:   game::Config,
+    // put on high scores
+    pub name:     String,
+    // how much bigger than normal to draw the menus, and the board in a fresh window
+    pub scale:    f32,
+    pub theme:    Theme,
+    pub bindings: Bindings,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            config: game::Config {
+                width:      12,
+                height:     9,
+                n_mines:    13,
+                seed:       None,
+                generation: game::Generation::NoGuess,
+                opening:    game::Opening::SafeArea,
+                chording:   game::Chording::Standard,
+                win:        game::WinRule::Cleared,
+                flags:      game::FlagLimit::Unlimited,
+                question_marks: true,
+                topology:   game::Topology::Flat,
+                tiling:     grid::Tiling::Square,
+                neighbourhood: grid::Neighbourhood::Adjacent,
+                outline:    game::Outline::Rectangle,
+                mines_per_tile: 1,
+            },
+            name:     std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "player".to_owned()),
+            scale:    1.,
+            theme:    Theme::Dark,
+            bindings: Bindings::default(),
+        }
+    }
+}
+
+fn path(ctx: &ggez::Context) -> PathBuf {
+    ggez::filesystem::user_config_dir(ctx).join(FILE)
+}
+
+fn warn(path: &Path, err: impl fmt::Display) {
+    eprintln!("{}: {}; using the default", path.display(), err);
+}
+
+// the value of `key`, or `default` with a warning if it doesn't parse
+fn lenient<T> (path: &Path, fields: &Fields, key: &str, default: T) -> T
+    where T: FromStr + Clone
+{
+    fields.get_or(key, default.clone()).unwrap_or_else(|err| {
+        warn(path, err);
+        default
+    })
+}
+
+fn read_fields(path: &Path, fields: &Fields) -> Settings {
+    for (line, key) in fields.keys() {
+        if !KEYS.contains(&key) {
+            eprintln!("{}: line {}: unknown setting '{}'", path.display(), line, key);
+        }
+    }
+
+    let defaults = Settings::default();
+
+    let seed: String = lenient(path, fields, "seed", "random".to_owned());
+    let seed = match seed.as_str() {
+        "random" => None,
+        other    => other.parse()
+            .map_err(|_| warn(path, format!("bad seed '{}'", other)))
+            .ok(),
+    };
+
+    let d = defaults.config;
+    let mut config = game::Config {
+        width:      lenient(path, fields, "width",      d.width),
+        height:     lenient(path, fields, "height",     d.height),
+        n_mines:    lenient(path, fields, "mines",      d.n_mines),
+        seed,
+        generation: lenient(path, fields, "generation", d.generation),
+        opening:    lenient(path, fields, "opening",    d.opening),
+        chording:   lenient(path, fields, "chording",   d.chording),
+        win:        lenient(path, fields, "win",        d.win),
+        flags:      lenient(path, fields, "flags",      d.flags),
+        question_marks: lenient(path, fields, "question-marks", d.question_marks),
+        topology:   lenient(path, fields, "topology",   d.topology),
+        tiling:     lenient(path, fields, "tiling",     d.tiling),
+        neighbourhood: lenient(path, fields, "neighbourhood", d.neighbourhood),
+        outline:    lenient(path, fields, "outline",    d.outline.clone()),
+        mines_per_tile: lenient(path, fields, "mines-per-tile", d.mines_per_tile),
+    };
+
+    if !config.is_possible() {
+        warn(path, "impossible board dimensions");
+        config = game::Config { width: d.width, height: d.height, n_mines: d.n_mines, outline: d.outline,
+            topology: d.topology, mines_per_tile: d.mines_per_tile, ..config };
+    }
+
+    let mut scale = lenient(path, fields, "scale", defaults.scale);
+    if !(MIN_SCALE ..= MAX_SCALE).contains(&scale) {
+        warn(path, format!("scale {} is out of range", scale));
+        scale = defaults.scale;
+    }
+
+    let b = defaults.bindings;
+    Settings {
+        config,
+        name: lenient(path, fields, "name", defaults.name),
+        scale,
+        theme: lenient(path, fields, "theme", defaults.theme),
+        bindings: Bindings {
+            dig:       lenient(path, fields, "bind-dig",       b.dig),
+            flag:      lenient(path, fields, "bind-flag",      b.flag),
+            chord:     lenient(path, fields, "bind-chord",     b.chord),
+            undo:      lenient(path, fields, "bind-undo",      b.undo),
+            redo:      lenient(path, fields, "bind-redo",      b.redo),
+            hint:      lenient(path, fields, "bind-hint",      b.hint),
+            up:        lenient(path, fields, "bind-up",        b.up),
+            down:      lenient(path, fields, "bind-down",      b.down),
+            left:      lenient(path, fields, "bind-left",      b.left),
+            right:     lenient(path, fields, "bind-right",     b.right),
+            restart:   lenient(path, fields, "bind-restart",   b.restart),
+            configure: lenient(path, fields, "bind-configure", b.configure),
+        },
+    }
+}
+
+// read the settings file, writing out the defaults if there isn't one yet
+pub fn load(ctx: &ggez::Context) -> Settings {
+    let path = path(ctx);
+    let file = match std::fs::File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let settings = Settings::default();
+            save(ctx, &settings);
+            return settings;
+        }
+        Err(err) => {
+            warn(&path, err);
+            return Settings::default();
+        }
+    };
+
+    let mut reader = Reader::new(std::io::BufReader::new(file));
+    let fields = reader.header(KIND, VERSION).and_then(|_| reader.fields());
+    match fields {
+        Ok(fields) => read_fields(&path, &fields),
+        Err(err) => {
+            warn(&path, err);
+            Settings::default()
+        }
+    }
+}
+
+pub fn save(ctx: &ggez::Context, settings: &Settings) {
+    let path = path(ctx);
+    let result = std::fs::create_dir_all(ggez::filesystem::user_config_dir(ctx))
+        .and_then(|_| std::fs::File::create(&path))
+        .and_then(|file| {
+            let mut w = std::io::BufWriter::new(file);
+            writeln!(w, "{} {}", KIND, VERSION)?;
+            format::write_config(&mut w, &settings.config)?;
+            writeln!(w, "name {}",      settings.name)?;
+            writeln!(w, "scale {}",     settings.scale)?;
+            writeln!(w, "theme {}",     settings.theme)?;
+            let b = &settings.bindings;
+            writeln!(w, "bind-dig {}",       b.dig)?;
+            writeln!(w, "bind-flag {}",      b.flag)?;
+            writeln!(w, "bind-chord {}",     b.chord)?;
+            writeln!(w, "bind-undo {}",      b.undo)?;
+            writeln!(w, "bind-redo {}",      b.redo)?;
+            writeln!(w, "bind-hint {}",      b.hint)?;
+            writeln!(w, "bind-up {}",        b.up)?;
+            writeln!(w, "bind-down {}",      b.down)?;
+            writeln!(w, "bind-left {}",      b.left)?;
+            writeln!(w, "bind-right {}",     b.right)?;
+            writeln!(w, "bind-restart {}",   b.restart)?;
+            writeln!(w, "bind-configure {}", b.configure)?;
+            w.flush()
+        });
+
+    if let Err(err) = result {
+        eprintln!("couldn't save settings to {}: {}", path.display(), err);
+    }
+}
@@ -0,0 +1,565 @@
+
+// deductions about covered tiles, drawn only from what a player can see: the numbers on
+// uncovered tiles, the flags, and the total mine count. `TileKind` is never looked at.
+//
+// flags are trusted. a wrong flag can make the board contradictory, in which case the
+// constraints it spoils are skipped rather than reasoned from.
+
+use {
+    crate::{
+        game::{Mark, State, Tile, TileState},
+        grid::{Coords, Grid},
+    },
+};
+
+// what a player can see of a tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cell {
+    Hidden,
+    Flagged,
+    Clue(usize),
+}
+
+// `mines` of the hidden tiles in `cells` are mines
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Constraint {
+    cells: Vec<usize>,
+    mines: usize,
+}
+
+impl Constraint {
+    fn is_subset_of(&self, other: &Constraint) -> bool {
+        self.cells.len() < other.cells.len()
+            && self.cells.iter().all(|c| other.cells.binary_search(c).is_ok())
+    }
+
+    // `None` if the two contradict each other, which wrong flags can bring about
+    fn minus(&self, other: &Constraint) -> Option<Constraint> {
+        let cells: Vec<usize> = self.cells.iter()
+            .copied()
+            .filter(|c| other.cells.binary_search(c).is_err())
+            .collect();
+        let mines = self.mines.checked_sub(other.mines)?;
+        if mines > cells.len() { return None; }
+        Some(Constraint { cells, mines })
+    }
+
+    fn trivial(&self) -> Option<bool> {
+             if self.mines == 0                { Some(false) }
+        else if self.mines == self.cells.len() { Some(true)  }
+        else                                    { None        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Deductions {
+    pub safe:  Vec<Coords>,
+    pub mines: Vec<Coords>,
+}
+
+impl Deductions {
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+fn view(tiles: &Grid<Tile>) -> Grid<Cell> {
+    Grid::new_generate(tiles.width(), tiles.height(), |ij| {
+        match tiles[ij].state {
+            TileState::Covered(Mark::Flag(_)) => Cell::Flagged,
+            // a question mark is only a note to self
+            TileState::Covered(_)             => Cell::Hidden,
+            TileState::Uncovered              => Cell::Clue(tiles[ij].n_near),
+        }
+    }).shaped(tiles.shape()).masked(tiles.mask().cloned())
+}
+
+fn index(cells: &Grid<Cell>, ij: Coords) -> usize {
+    ij.y as usize * cells.width() + ij.x as usize
+}
+
+fn coords(cells: &Grid<Cell>, c: usize) -> Coords {
+    Coords::new((c % cells.width()) as i32, (c / cells.width()) as i32)
+}
+
+fn constraints(cells: &Grid<Cell>) -> Vec<Constraint> {
+    let mut constraints: Vec<Constraint> = cells.enumerate()
+        .filter_map(|(ij, cell)| match *cell {
+            Cell::Clue(n_near) => Some((ij, n_near)),
+            _                  => None
+        })
+        .filter_map(|(ij, n_near)| {
+            let mut hidden = Vec::new();
+            let mut flagged = 0;
+            for adj in cells.neighbours(ij) {
+                match cells[adj] {
+                    Cell::Hidden  => hidden.push(index(cells, adj)),
+                    Cell::Flagged => flagged += 1,
+                    Cell::Clue(_) => { }
+                }
+            }
+
+            if hidden.is_empty() || flagged > n_near || n_near - flagged > hidden.len() {
+                return None;
+            }
+
+            hidden.sort();
+            Some(Constraint { cells: hidden, mines: n_near - flagged })
+        })
+        .collect();
+
+    constraints.sort_by(|a, b| a.cells.cmp(&b.cells));
+    constraints.dedup();
+    constraints
+}
+
+// derive new constraints from pairs where one is contained in the other, until nothing new
+// turns up
+fn reduce(constraints: &mut Vec<Constraint>) {
+    const MAX_CONSTRAINTS: usize = 4096;
+
+    let mut fresh = 0;
+    while fresh < constraints.len() && constraints.len() < MAX_CONSTRAINTS {
+        let end = constraints.len();
+        let mut derived = Vec::new();
+        for i in fresh .. end {
+            for j in 0 .. end {
+                let (a, b) = (&constraints[i], &constraints[j]);
+                let c =      if a.is_subset_of(b) { b.minus(a) }
+                        else if b.is_subset_of(a) { a.minus(b) }
+                        else                      { continue };
+                let c = match c {
+                    Some(c) => c,
+                    None    => continue
+                };
+                if !constraints.contains(&c) && !derived.contains(&c) {
+                    derived.push(c);
+                }
+            }
+        }
+
+        fresh = end;
+        constraints.extend(derived);
+    }
+}
+
+// for each cell of a group: whether it's a mine in some solution, and whether it's safe in some
+type Seen = (Vec<bool>, Vec<bool>);
+
+// the ways a group of linked cells can be filled, by number of mines used. `None` means the
+// group was too big to enumerate, and anything goes.
+struct Solutions {
+    cells:    Vec<usize>,
+    by_count: Option<Vec<Option<Seen>>>,
+}
+
+impl Solutions {
+    fn counts(&self) -> Vec<bool> {
+        match &self.by_count {
+            Some(by_count) => by_count.iter().map(Option::is_some).collect(),
+            None           => vec![true; self.cells.len() + 1],
+        }
+    }
+}
+
+// give up on a component once the search has visited this many partial assignments
+const MAX_SEARCH_NODES: usize = 1 << 20;
+
+fn enumerate(cells: Vec<usize>, constraints: Vec<Constraint>) -> Solutions {
+    // which constraints each cell appears in
+    let touching: Vec<Vec<usize>> = cells.iter()
+        .map(|c| {
+            constraints.iter()
+                .enumerate()
+                .filter(|(_, constraint)| constraint.cells.binary_search(c).is_ok())
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .collect();
+
+    struct Search<'a> {
+        touching:    &'a [Vec<usize>],
+        constraints: &'a [Constraint],
+        // mines placed and cells still open, per constraint
+        placed:      Vec<usize>,
+        open:        Vec<usize>,
+        assignment:  Vec<bool>,
+        by_count:    Vec<Option<Seen>>,
+        nodes:       usize,
+    }
+
+    impl<'a> Search<'a> {
+        fn fits(&self, i: usize) -> bool {
+            self.touching[i].iter().all(|&k| {
+                let need = self.constraints[k].mines;
+                self.placed[k] <= need && self.placed[k] + self.open[k] >= need
+            })
+        }
+
+        fn set(&mut self, i: usize, mine: bool) {
+            self.assignment[i] = mine;
+            for &k in &self.touching[i] {
+                self.open[k] -= 1;
+                if mine { self.placed[k] += 1; }
+            }
+        }
+
+        fn unset(&mut self, i: usize) {
+            for &k in &self.touching[i] {
+                self.open[k] += 1;
+                if self.assignment[i] { self.placed[k] -= 1; }
+            }
+        }
+
+        // false if the search was abandoned
+        fn go(&mut self, i: usize) -> bool {
+            self.nodes += 1;
+            if self.nodes > MAX_SEARCH_NODES {
+                return false;
+            }
+
+            if i == self.assignment.len() {
+                let count = self.assignment.iter().filter(|&&mine| mine).count();
+                let n = self.assignment.len();
+                let entry = self.by_count[count].get_or_insert_with(|| (vec![false; n], vec![false; n]));
+                for (j, &mine) in self.assignment.iter().enumerate() {
+                    if mine { entry.0[j] = true; } else { entry.1[j] = true; }
+                }
+                return true;
+            }
+
+            for &mine in &[false, true] {
+                self.set(i, mine);
+                let ok = !self.fits(i) || self.go(i + 1);
+                self.unset(i);
+                if !ok { return false; }
+            }
+
+            true
+        }
+    }
+
+    let mut search = Search {
+        touching:    &touching,
+        constraints: &constraints,
+        placed:      vec![0; constraints.len()],
+        open:        constraints.iter().map(|constraint| constraint.cells.len()).collect(),
+        assignment:  vec![false; cells.len()],
+        by_count:    vec![None; cells.len() + 1],
+        nodes:       0,
+    };
+
+    let by_count = if search.go(0) { Some(search.by_count) } else { None };
+    Solutions { cells, by_count }
+}
+
+// split the frontier into groups of cells that share no constraints, ordering each group so
+// that neighbouring cells are assigned one after another
+fn components(constraints: &[Constraint]) -> Vec<(Vec<usize>, Vec<Constraint>)> {
+    let mut done = vec![false; constraints.len()];
+    let mut components = Vec::new();
+
+    for first in 0 .. constraints.len() {
+        if done[first] { continue; }
+        done[first] = true;
+
+        let mut cells: Vec<usize> = Vec::new();
+        let mut members = vec![first];
+        let mut next = 0;
+        while next < members.len() {
+            let constraint = &constraints[members[next]];
+            next += 1;
+
+            for &c in &constraint.cells {
+                if cells.contains(&c) { continue; }
+                cells.push(c);
+
+                for (k, other) in constraints.iter().enumerate() {
+                    if !done[k] && other.cells.binary_search(&c).is_ok() {
+                        done[k] = true;
+                        members.push(k);
+                    }
+                }
+            }
+        }
+
+        let members = members.into_iter().map(|k| constraints[k].clone()).collect();
+        components.push((cells, members));
+    }
+
+    components
+}
+
+// sums reachable by picking one allowed count from each of `sets`
+fn sums(sets: &[Vec<bool>]) -> Vec<bool> {
+    let mut reachable = vec![true];
+    for set in sets {
+        let mut next = vec![false; reachable.len() + set.len() - 1];
+        for (a, _) in reachable.iter().enumerate().filter(|(_, &r)| r) {
+            for (b, _) in set.iter().enumerate().filter(|(_, &s)| s) {
+                next[a + b] = true;
+            }
+        }
+        reachable = next;
+    }
+    reachable
+}
+
+// `n_mines` is the total on the board, if known; it lets the solver reason about tiles away
+// from the frontier, and about how many mines the frontier can hold
+pub fn deduce_tiles(tiles: &Grid<Tile>, n_mines: Option<usize>) -> Deductions {
+    let cells = view(tiles);
+    let n_cells = cells.width() * cells.height();
+
+    // single-constraint and subset reasoning
+    let base = constraints(&cells);
+    let mut derived = base.clone();
+    reduce(&mut derived);
+
+    let mut known: Vec<Option<bool>> = vec![None; n_cells];
+    for constraint in &derived {
+        if let Some(mine) = constraint.trivial() {
+            for &c in &constraint.cells {
+                known[c] = Some(mine);
+            }
+        }
+    }
+
+    // what's left of the frontier once the easy answers are filled in
+    let mut frontier: Vec<Constraint> = base.iter()
+        .filter_map(|constraint| {
+            let mines = constraint.cells.iter().filter(|&&c| known[c] == Some(true)).count();
+            let cells: Vec<usize> = constraint.cells.iter()
+                .copied()
+                .filter(|&c| known[c].is_none())
+                .collect();
+            if cells.is_empty() || mines > constraint.mines {
+                None
+            }
+            else {
+                Some(Constraint { cells, mines: constraint.mines - mines })
+            }
+        })
+        .collect();
+    frontier.sort_by(|a, b| a.cells.cmp(&b.cells));
+    frontier.dedup();
+
+    // full enumeration of each independent part of the frontier
+    let solutions: Vec<Solutions> = components(&frontier).into_iter()
+        .map(|(cells, constraints)| enumerate(cells, constraints))
+        .collect();
+
+    let mut in_frontier = vec![false; n_cells];
+    for solution in &solutions {
+        for &c in &solution.cells {
+            in_frontier[c] = true;
+        }
+    }
+
+    let interior: Vec<usize> = cells.enumerate()
+        .map(|(ij, cell)| (index(&cells, ij), cell))
+        .filter(|&(c, cell)| *cell == Cell::Hidden && known[c].is_none() && !in_frontier[c])
+        .map(|(c, _)| c)
+        .collect();
+
+    // bring in the global mine count, working out which counts each part can really have
+    let mut counts: Vec<Vec<bool>> = solutions.iter().map(Solutions::counts).collect();
+    let mut interior_counts = vec![true; interior.len() + 1];
+
+    let remaining = n_mines.and_then(|n_mines| {
+        let flagged = cells.iter().filter(|&&cell| cell == Cell::Flagged).count();
+        let found   = known.iter().filter(|&&mine| mine == Some(true)).count();
+        n_mines.checked_sub(flagged + found)
+    });
+
+    if let Some(remaining) = remaining {
+        // whether the frontier holding `sum` mines leaves a number the interior can hold
+        let fits = |sum: usize| sum <= remaining && remaining - sum <= interior.len();
+
+        let feasible: Vec<Vec<bool>> = (0 .. counts.len())
+            .map(|i| {
+                let others: Vec<Vec<bool>> = counts.iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, set)| set.clone())
+                    .collect();
+                let others = sums(&others);
+                counts[i].iter()
+                    .enumerate()
+                    .map(|(k, &allowed)| {
+                        allowed && others.iter().enumerate().any(|(s, &r)| r && fits(k + s))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let all = sums(&counts);
+        let interior_feasible: Vec<bool> = (0 ..= interior.len())
+            .map(|n| all.iter().enumerate().any(|(s, &r)| r && s + n == remaining))
+            .collect();
+
+        // a contradiction means the flags are wrong somewhere; say nothing rather than guess
+        if interior_feasible.iter().any(|&f| f) {
+            counts = feasible;
+            interior_counts = interior_feasible;
+        }
+    }
+
+    for (solution, counts) in solutions.iter().zip(&counts) {
+        let by_count = match &solution.by_count {
+            Some(by_count) => by_count,
+            None           => continue
+        };
+
+        let mut can_mine = vec![false; solution.cells.len()];
+        let mut can_safe = vec![false; solution.cells.len()];
+        let mut any = false;
+        for (k, entry) in by_count.iter().enumerate() {
+            if let (true, Some((mine, safe))) = (counts[k], entry) {
+                any = true;
+                for j in 0 .. solution.cells.len() {
+                    can_mine[j] |= mine[j];
+                    can_safe[j] |= safe[j];
+                }
+            }
+        }
+
+        if !any { continue; }
+
+        for (j, &c) in solution.cells.iter().enumerate() {
+            match (can_mine[j], can_safe[j]) {
+                (false, true) => known[c] = Some(false),
+                (true, false) => known[c] = Some(true),
+                _             => { }
+            }
+        }
+    }
+
+    if !interior.is_empty() {
+        let possible: Vec<usize> = interior_counts.iter()
+            .enumerate()
+            .filter(|(_, &f)| f)
+            .map(|(n, _)| n)
+            .collect();
+
+        let mine = match possible.as_slice() {
+            [0]                         => Some(false),
+            [n] if *n == interior.len() => Some(true),
+            _                           => None
+        };
+
+        if let Some(mine) = mine {
+            for &c in &interior {
+                known[c] = Some(mine);
+            }
+        }
+    }
+
+    let mut deductions = Deductions::default();
+    for (c, mine) in known.into_iter().enumerate() {
+        match mine {
+            Some(false) => deductions.safe.push(coords(&cells, c)),
+            Some(true)  => deductions.mines.push(coords(&cells, c)),
+            None        => { }
+        }
+    }
+
+    deductions
+}
+
+pub fn deduce(state: &State) -> Deductions {
+    // the solver only knows tiles holding one mine or none
+    if state.config().mines_per_tile > 1 { return Deductions::default(); }
+    deduce_tiles(state.tiles(), Some(state.config().n_mines))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::game::TileKind,
+    };
+
+    // '.' covered, 'F' flagged, a digit uncovered with that many around. what's under the
+    // covered tiles is never looked at.
+    fn board(rows: &[&str]) -> Grid<Tile> {
+        let rows: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        Grid::new_generate(rows[0].len(), rows.len(), |ij| {
+            let (state, n_near) = match rows[ij.y as usize][ij.x as usize] {
+                '.' => (TileState::Covered(Mark::Blank), 0),
+                'F' => (TileState::Covered(Mark::Flag(1)), 0),
+                ch  => (TileState::Uncovered, ch.to_digit(10).unwrap() as usize),
+            };
+            Tile { kind: TileKind::Dirt, state, n_near }
+        })
+    }
+
+    fn sorted(mut coords: Vec<Coords>) -> Vec<Coords> {
+        coords.sort_by_key(|p| (p.y, p.x));
+        coords
+    }
+
+    #[test]
+    fn single_clues() {
+        let deductions = deduce_tiles(&board(&["1..0"]), None);
+        assert_eq!(deductions.mines, vec![Coords::new(1, 0)]);
+        assert_eq!(deductions.safe,  vec![Coords::new(2, 0)]);
+    }
+
+    #[test]
+    fn one_two_one() {
+        let deductions = deduce_tiles(&board(&["...", "121"]), None);
+        assert_eq!(sorted(deductions.mines), vec![Coords::new(0, 0), Coords::new(2, 0)]);
+        assert_eq!(deductions.safe, vec![Coords::new(1, 0)]);
+    }
+
+    #[test]
+    fn mine_count() {
+        let deductions = deduce_tiles(&board(&["F..", "..."]), Some(1));
+        assert!(deductions.mines.is_empty());
+        assert_eq!(deductions.safe.len(), 5);
+    }
+
+    // no pair of clues here is one inside the other, so it takes trying every way of filling
+    // the frontier
+    #[test]
+    fn enumeration() {
+        let rows = [".....", "....2", "..31."];
+        let cells = view(&board(&rows));
+        let mut derived = constraints(&cells);
+        reduce(&mut derived);
+        assert!(derived.iter().all(|constraint| constraint.trivial().is_none()));
+
+        let deductions = deduce_tiles(&board(&rows), None);
+        assert_eq!(sorted(deductions.mines), vec![Coords::new(1, 1), Coords::new(1, 2)]);
+        assert_eq!(deductions.safe, vec![Coords::new(4, 2)]);
+    }
+
+    // nothing follows from the clues alone, but only one way of filling the frontier leaves the
+    // right number of mines for the rest of the board
+    #[test]
+    fn frontier_and_mine_count() {
+        let rows = ["..2.1", "3....", "...1."];
+        let deductions = deduce_tiles(&board(&rows), None);
+        assert!(deductions.is_empty());
+
+        let deductions = deduce_tiles(&board(&rows), Some(5));
+        assert_eq!(deductions.mines, vec![Coords::new(3, 0)]);
+        assert_eq!(sorted(deductions.safe), vec![Coords::new(3, 1), Coords::new(4, 1)]);
+    }
+
+    #[test]
+    fn contradiction() {
+        // as a wrong flag can leave things: the right clue wants one mine of the four tiles
+        // around it, but the left wants two of those. the left still has its say; the difference
+        // between them, which would be minus one mine, is dropped.
+        let deductions = deduce_tiles(&board(&["....", "21.."]), None);
+        assert_eq!(sorted(deductions.mines), vec![Coords::new(0, 0), Coords::new(1, 0)]);
+        assert!(deductions.safe.is_empty());
+
+        // three mines the clues can't do without, on a board said to hold two
+        let deductions = deduce_tiles(&board(&["...", "212"]), Some(2));
+        assert_eq!(sorted(deductions.mines), vec![Coords::new(0, 0), Coords::new(1, 0), Coords::new(2, 0)]);
+        assert!(deductions.safe.is_empty());
+    }
+}
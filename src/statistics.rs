@@ -0,0 +1,186 @@
+
+use {
+    crate::{
+        activity::*,
+        game,
+        maths::*,
+        records::{self, ROW_HEIGHT},
+        scores::Board,
+        stats::{Record, Stats},
+    },
+    ggez::{
+        GameResult,
+        input::{
+            keyboard::{KeyCode, KeyMods},
+            mouse::MouseButton,
+        },
+    },
+};
+
+const WINDOW_WIDTH: f32 = 420.;
+
+// the title, a row per figure, then reset and the way back
+const N_FIGURES: usize = 9;
+const N_ROWS:    usize = N_FIGURES + 3;
+
+// kept with the settings, rather than with the high scores
+const FILE: &str = "stats";
+
+fn path(ctx: &ggez::Context) -> std::path::PathBuf {
+    ggez::filesystem::user_config_dir(ctx).join(FILE)
+}
+
+// count a game that's over or being given up on
+pub fn add(ctx: &ggez::Context, state: &game::State, clicks: usize) {
+    records::update(&path(ctx), |stats: &mut Stats| stats.add(state, clicks));
+}
+
+fn seconds(time: Option<u64>) -> String {
+    time.map_or_else(|| "-".to_owned(), |time| format!("{:.3}s", time as f64 * 0.001))
+}
+
+fn decimal(x: Option<f64>) -> String {
+    x.map_or_else(|| "-".to_owned(), |x| format!("{:.2}", x))
+}
+
+fn figures(r: &Record) -> [(&'static str, String); N_FIGURES] {
+    let won = if r.played == 0 {
+        r.won.to_string()
+    }
+    else {
+        format!("{} ({:.0}%)", r.won, r.won as f64 * 100. / r.played as f64)
+    };
+
+    [
+        ("played",          r.played.to_string()),
+        ("won",             won),
+        ("lost",            r.lost.to_string()),
+        ("win streak",      r.streak.to_string()),
+        ("best streak",     r.best_streak.to_string()),
+        ("best time",       seconds(r.best_time)),
+        ("average time",    seconds(r.average_time())),
+        ("average 3BV/s",   decimal(r.average_rate())),
+        ("clicks per game", decimal(r.clicks_per_game())),
+    ]
+}
+
+pub struct Statistics {
+    board:  Board,
+    // `None` if the file couldn't be read
+    stats:  Option<Stats>,
+    // reset has been asked for once, and wants asking again
+    confirming: bool,
+    dirty:  bool,
+    switch: Option<Switch>,
+}
+
+impl Statistics {
+    pub fn new(ctx: &ggez::Context, config: &game::Config) -> Statistics {
+        Statistics {
+            board:  Board::new(config),
+            stats:  records::try_load(&path(ctx)),
+            confirming: false,
+            dirty:  true,
+            switch: None,
+        }
+    }
+
+    fn reset(&mut self, ctx: &ggez::Context) {
+        if !self.confirming {
+            self.confirming = true;
+            return;
+        }
+
+        // from the file as it is now, and not at all if it can't be read
+        let board = &self.board;
+        let path = path(ctx);
+        self.stats = records::update(&path, |stats: &mut Stats| stats.reset(board))
+            .and_then(|_| records::try_load(&path));
+        self.confirming = false;
+    }
+}
+
+impl Activity for Statistics {
+    fn mouse_down<'a> (&mut self, Context { ctx, .. }: Context<'a>, button: MouseButton, position: P2) {
+        if button != MouseButton::Left { return; }
+
+        match (position.y / ROW_HEIGHT) as usize {
+            i if i == N_ROWS - 2 => self.reset(ctx),
+            i if i == N_ROWS - 1 => self.switch = Some(Switch::Back),
+            _                    => self.confirming = false,
+        }
+
+        self.dirty = true;
+    }
+
+    fn mouse_up<'a> (&mut self, _context: Context<'a>, _button: MouseButton, _position: P2) {
+    }
+
+    fn mouse_move<'a> (&mut self, _context: Context<'a>, _position: P2) {
+    }
+
+    fn resized<'a> (&mut self, _context: Context<'a>, _width: f32, _height: f32) {
+    }
+
+    fn key_down<'a> (&mut self, _context: Context<'a>, key: KeyCode, _mods: KeyMods) {
+        match key {
+            KeyCode::Escape | KeyCode::Return => self.switch = Some(Switch::Back),
+            _                                 => return
+        }
+
+        self.dirty = true;
+    }
+
+    fn draw<'a> (
+        &mut self,
+        Context { ctx, assets, settings, .. }: Context<'a>)
+        -> GameResult
+    {
+        use ggez::graphics::{Align, draw, DrawParam};
+
+        let board = &self.board;
+        let title = format!("{}×{}, {} mines", board.width, board.height, board.n_mines);
+        records::draw_frame(ctx, assets, settings, WINDOW_WIDTH, N_ROWS, title)?;
+        let label = |text: String, align: Align| records::label(assets, WINDOW_WIDTH, text, align);
+
+        match &self.stats {
+            Some(stats) => {
+                let record = stats.record(&self.board);
+                for (i, (name, value)) in figures(&record).iter().enumerate() {
+                    let dest = records::row_dest(i + 1);
+                    let mut name = label(name.to_string(), Align::Left);
+                    name.fragments_mut()[0].color = Some((0.6, 0.6, 0.6).into());
+                    draw(ctx, &name, DrawParam::new().dest(dest))?;
+                    draw(ctx, &label(value.clone(), Align::Right), DrawParam::new().dest(dest))?;
+                }
+            }
+
+            // and nothing will be saved over it
+            None => {
+                let warning = label("couldn't read the statistics file".to_owned(), Align::Center);
+                draw(ctx, &warning, DrawParam::new().dest(records::row_dest(1)))?;
+            }
+        }
+
+        let reset = if self.confirming { "reset? click again" } else { "reset" };
+        draw(ctx, &label(reset.to_owned(), Align::Center), DrawParam::new().dest(records::row_dest(N_ROWS - 2)))?;
+        draw(ctx, &label("back".to_owned(), Align::Center), DrawParam::new().dest(records::row_dest(N_ROWS - 1)))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn close<'a> (&mut self, _context: Context<'a>) {
+    }
+
+    fn focus<'a> (&mut self, _context: Context<'a>, _focused: bool) {
+    }
+
+    fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn switch(&mut self) -> Option<Switch> {
+        self.switch.take()
+    }
+}
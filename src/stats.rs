@@ -0,0 +1,171 @@
+
+// lifetime totals for each kind of board, so that a player can see how they're getting on over
+// weeks rather than single games. every finished game counts, helped or not, and so does one
+// given up on part way through.
+
+use {
+    crate::{
+        format::{self, Error, Reader},
+        game::{State, Status},
+        metrics,
+        scores::Board,
+    },
+    std::io::{BufRead, Write},
+};
+
+const KIND:    &str = "mines-stats";
+const VERSION: u32  = 1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub played:      u64,
+    pub won:         u64,
+    pub lost:        u64,
+    // wins in a row, up to the last game
+    pub streak:      u64,
+    pub best_streak: u64,
+    // summed over wins only, in milliseconds
+    pub win_time:    u64,
+    pub best_time:   Option<u64>,
+    // summed over wins only
+    pub win_bbbv:    u64,
+    // dig and flag clicks, over every game
+    pub clicks:      u64,
+}
+
+impl Record {
+    // count a game that's over, or that was left unfinished
+    pub fn add(&mut self, state: &State, clicks: usize) {
+        self.played += 1;
+        self.clicks += clicks as u64;
+
+        match state.status() {
+            Status::Won => {
+                let time = state.elapsed();
+                self.won += 1;
+                self.streak += 1;
+                self.best_streak = self.best_streak.max(self.streak);
+                self.win_time += time;
+                self.best_time = Some(self.best_time.map_or(time, |best| best.min(time)));
+                self.win_bbbv += metrics::measure(state.tiles()).bbbv as u64;
+            }
+
+            Status::Dead => {
+                self.lost += 1;
+                self.streak = 0;
+            }
+
+            Status::Playing => self.streak = 0,
+        }
+    }
+
+    // in milliseconds
+    pub fn average_time(&self) -> Option<u64> {
+        if self.won == 0 { return None; }
+        Some(self.win_time / self.won)
+    }
+
+    // over all wins together, so that long games weigh more than quick ones
+    pub fn average_rate(&self) -> Option<f64> {
+        if self.won == 0 { return None; }
+        Some(metrics::rate(self.win_bbbv as usize, self.win_time))
+    }
+
+    pub fn clicks_per_game(&self) -> Option<f64> {
+        if self.played == 0 { return None; }
+        Some(self.clicks as f64 / self.played as f64)
+    }
+}
+
+#[derive(Default)]
+pub struct Stats {
+    records: Vec<(Board, Record)>,
+}
+
+impl Stats {
+    pub fn record(&self, board: &Board) -> Record {
+        self.records.iter()
+            .find(|(b, _)| b == board)
+            .map_or_else(Record::default, |&(_, record)| record)
+    }
+
+    fn record_mut(&mut self, board: Board) -> &mut Record {
+        let i = match self.records.iter().position(|(b, _)| *b == board) {
+            Some(i) => i,
+            None => {
+                self.records.push((board, Record::default()));
+                self.records.len() - 1
+            }
+        };
+
+        &mut self.records[i].1
+    }
+
+    pub fn add(&mut self, state: &State, clicks: usize) {
+        self.record_mut(Board::played(state)).add(state, clicks);
+    }
+
+    // forget everything about one kind of board
+    pub fn reset(&mut self, board: &Board) {
+        self.records.retain(|(b, _)| b != board);
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> std::io::Result<()> {
+        writeln!(w, "{} {}", KIND, VERSION)?;
+        writeln!(w)?;
+
+        for (board, r) in &self.records {
+            writeln!(w, "board {}", board)?;
+            writeln!(w, "record {} {} {} {} {} {} {} {} {}",
+                r.played, r.won, r.lost, r.streak, r.best_streak,
+                r.win_time, r.best_time.unwrap_or(0), r.win_bbbv, r.clicks)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(r: impl BufRead) -> Result<Stats, Error> {
+        let mut reader = Reader::new(r);
+        reader.header(KIND, VERSION)?;
+        reader.fields()?;
+
+        let mut stats = Stats::default();
+        let mut board = None;
+        while let Some((line, text)) = reader.body_line()? {
+            let mut words = text.split_whitespace();
+            match words.next() {
+                Some("board") => board = Some(Board::read(line, &mut words)?),
+
+                Some("record") => {
+                    let mut record = Record {
+                        played:      format::parse(line, words.next(), "played")?,
+                        won:         format::parse(line, words.next(), "won")?,
+                        lost:        format::parse(line, words.next(), "lost")?,
+                        streak:      format::parse(line, words.next(), "streak")?,
+                        best_streak: format::parse(line, words.next(), "best streak")?,
+                        win_time:    format::parse(line, words.next(), "total time")?,
+                        best_time:   Some(format::parse(line, words.next(), "best time")?),
+                        win_bbbv:    format::parse(line, words.next(), "total 3bv")?,
+                        clicks:      format::parse(line, words.next(), "clicks")?,
+                    };
+                    // there's no best time until the first win
+                    if record.won == 0 {
+                        record.best_time = None;
+                    }
+
+                    let board = board.take().ok_or_else(|| {
+                        Error::Syntax { line, message: "record before any board".to_owned() }
+                    })?;
+                    *stats.record_mut(board) = record;
+                }
+
+                other => {
+                    let message = format!("unknown line '{}'", other.unwrap_or_default());
+                    return Err(Error::Syntax { line, message });
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}